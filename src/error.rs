@@ -1,4 +1,4 @@
-use crate::lexer::TokenType;
+use crate::lexer::{Span, TokenType};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,19 +6,20 @@ pub enum ConstantError {
     #[error("Please provide source file")]
     NoSourceFile,
 
+    #[error("Please provide source code after '{0}'")]
+    NoEvalSource(String),
+
     #[error("Could not find provided source file '{0}'")]
     SourceFileNotFound(String),
 
-    #[error(
-        "Too many arguments, either pass the sourse file or run with no arguments for REPL mode"
-    )]
-    TooManyArgs,
-
     #[error("String is not terminated before end of file")]
     StringNotTerminated,
 
-    #[error("Invalid string '{0}' at position {1}")]
-    InvalidString(String, usize),
+    #[error("Invalid escape sequence '\\{0}'")]
+    InvalidEscape(char),
+
+    #[error("Invalid string '{0}' at line {1}, column {2}")]
+    InvalidString(String, usize, usize),
 
     #[error("{0} requires at least {1} items on the stack")]
     InvalidStackAmount(String, usize),
@@ -26,12 +27,67 @@ pub enum ConstantError {
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
 
-    #[error("Unexpected Token: {0:?}")]
-    UnexpectedToken(TokenType),
+    #[error("Unexpected token {0:?} at {1}")]
+    UnexpectedToken(TokenType, Span),
+
+    #[error("'{0}' block starting at {1} is never closed")]
+    UnterminatedBlock(String, Span),
 
     #[error("Identifier '{0}' does not exist")]
     IdentDoesNotExist(String),
 
     #[error("Procedure '{0}' does not exist")]
     ProcDoesNotExist(String),
+
+    #[error("Stack effect error: {0}")]
+    StackEffectMismatch(String),
+
+    #[error("'{0}' is a constant and can't be reassigned")]
+    ConstantReassignment(String),
+
+    #[error("Procedure '{0}' is already defined")]
+    ProcAlreadyExists(String),
+
+    #[error("'{0}' can only be used inside of a loop")]
+    OutsideLoop(String),
+
+    #[error("Stack exceeded its maximum size of {0}")]
+    StackOverflow(usize),
+
+    #[error("Blocks were nested past the recursion limit of {0}")]
+    RecursionLimitExceeded(usize),
+
+    #[error("Assertion failed")]
+    AssertionFailed,
+
+    // not a failure, 'exit' stops the program by returning this so that
+    // embedders can decide what to do with the status code
+    #[error("Program exited with code {0}")]
+    Exit(i32),
+
+    #[error("Reached the end of input while reading")]
+    EndOfInput,
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl ConstantError {
+    // the status code the program exits with when this error stops it, so
+    // scripts can tell bad arguments and bad source apart from runtime errors
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ConstantError::Exit(code) => *code,
+            ConstantError::NoSourceFile
+            | ConstantError::NoEvalSource(_)
+            | ConstantError::SourceFileNotFound(_) => 2,
+            ConstantError::StringNotTerminated
+            | ConstantError::InvalidEscape(_)
+            | ConstantError::InvalidString(..)
+            | ConstantError::UnexpectedToken(..)
+            | ConstantError::UnterminatedBlock(..)
+            | ConstantError::StackEffectMismatch(_) => 3,
+            _ => 1,
+        }
+    }
 }
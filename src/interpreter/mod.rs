@@ -1,266 +1,3413 @@
-use std::{collections::HashMap, io::Write};
+use std::{
+    cmp::Ordering::{Equal, Greater, Less},
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
 
 use crate::{
     error::ConstantError,
-    lexer::{Lexer, Literal},
-    parser::{DoubleOpType, Parser, SingleOpType, Statement, Value},
+    lexer::{Lexer, Literal, Token, TokenType},
+    parser::{DoubleOpType, Parser, SingleOpType, StackOpType, Statement, TripleOpType, Value},
 };
 
+// every nested block costs a few kilobytes of native stack in a debug build,
+// this many fit well within the 2 MB that spawned threads get by default
+const DEFAULT_RECURSION_LIMIT: usize = 256;
+
+// 'rand' gives the same numbers on every run unless the program seeds it
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+// how execution continues after a statement has run
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+}
+
 pub struct Interpreter {
     stack: Vec<Literal>,
     program: Vec<Statement>,
-    idents: HashMap<String, Literal>,
+    // bindings from the outermost (global) scope to the innermost procedure call
+    scopes: Vec<HashMap<String, Literal>>,
+    // the names in each scope that were bound with 'const'
+    constants: Vec<HashSet<String>>,
     procs: HashMap<String, Vec<Statement>>,
+    // top-level procedures registered ahead of time whose definition hasn't been reached yet
+    pending_procs: HashSet<String>,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    args: Vec<String>,
+    // how many blocks are running inside each other
+    depth: usize,
+    recursion_limit: usize,
+    // where each statement and the stack after it are written when tracing
+    trace: Option<Box<dyn Write>>,
+    max_stack: Option<usize>,
+    strict: bool,
+    // xorshift state for 'rand', never zero
+    rng: u64,
 }
 
 impl Interpreter {
     pub fn new(program: Vec<Statement>) -> Self {
+        Self::with_writer(program, std::io::stdout())
+    }
+
+    // creates an interpreter that prints to the given writer instead of stdout
+    pub fn with_writer(program: Vec<Statement>, writer: impl Write + 'static) -> Self {
+        Self::with_io(program, BufReader::new(std::io::stdin()), writer)
+    }
+
+    // creates an interpreter that reads lines for 'read' and 'input' from the given
+    // reader instead of stdin, and prints to the given writer instead of stdout
+    pub fn with_io(
+        program: Vec<Statement>,
+        reader: impl BufRead + 'static,
+        writer: impl Write + 'static,
+    ) -> Self {
         Self {
             stack: Vec::new(),
             program,
-            idents: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            constants: vec![HashSet::new()],
             procs: HashMap::new(),
+            pending_procs: HashSet::new(),
+            input: Box::new(reader),
+            output: Box::new(writer),
+            args: Vec::new(),
+            depth: 0,
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+            trace: None,
+            max_stack: None,
+            strict: false,
+            rng: DEFAULT_SEED,
         }
     }
 
+    // sets the arguments the program can read with 'arg', there are none by default
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    // sets how deeply blocks can run inside each other, whether through procedure calls,
+    // 'apply', or nested loops and ifs, defaults to 256. raising it needs a thread with
+    // a larger stack to run on
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.recursion_limit = limit;
+    }
+
+    // prints every statement and the resulting stack to stderr as they run
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace.then(|| Box::new(std::io::stderr()) as Box<dyn Write>);
+    }
+
+    // traces to the given writer instead of stderr
+    pub fn set_trace_writer(&mut self, writer: impl Write + 'static) {
+        self.trace = Some(Box::new(writer));
+    }
+
+    // limits how many items the stack can hold, it is unlimited by default
+    pub fn set_max_stack(&mut self, max_stack: Option<usize>) {
+        self.max_stack = max_stack;
+    }
+
+    // makes defining a procedure that already exists an error, by default the last definition wins
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn stack(&self) -> &[Literal] {
+        &self.stack
+    }
+
+    pub fn into_stack(self) -> Vec<Literal> {
+        self.stack
+    }
+
     pub fn interpret(&mut self) -> Result<(), ConstantError> {
-        for statement in self.program.clone() {
-            self.interpret_statement(&statement)?;
+        // the program is moved out while it runs so it doesn't have to be cloned
+        let program = std::mem::take(&mut self.program);
+        self.register_procs(&program);
+
+        let res = self.interpret_block(&program);
+        self.program = program;
+
+        // output from 'write' may not end in a newline
+        self.output.flush()?;
+
+        match res? {
+            Flow::Break => Err(ConstantError::OutsideLoop("break".into())),
+            Flow::Continue => Err(ConstantError::OutsideLoop("continue".into())),
+            Flow::Normal => Ok(()),
         }
-        Ok(())
     }
 
-    fn interpret_statement(&mut self, statement: &Statement) -> Result<(), ConstantError> {
+    // an xorshift64 generator, the top 24 bits give every float in [0, 1) the same chance
+    fn next_random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // reads a line from the input without its line ending, erroring once the input has ended
+    fn read_line(&mut self) -> Result<String, ConstantError> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Err(ConstantError::EndOfInput);
+        }
+
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        Ok(line.into())
+    }
+
+    fn interpret_statement(&mut self, statement: &Statement) -> Result<Flow, ConstantError> {
+        // every statement that can run a block is handled in its own function, so
+        // that nesting only costs the native stack what each kind of statement needs
         match statement {
-            Statement::Push(Value::Literal(l)) => self.stack.push(l.clone()),
-            Statement::Push(Value::Ident(i)) => {
-                if let Some(v) = self.idents.get(i) {
-                    self.stack.push(v.clone());
-                } else {
-                    return Err(ConstantError::IdentDoesNotExist(i.into()));
-                }
+            Statement::Push(value) => self.push_value(value)?,
+            Statement::SingleOperation(o) => return self.single_operation(o),
+            Statement::DoubleOperation(o) => return self.double_operation(o),
+            Statement::TripleOperation(o) => return self.triple_operation(o),
+            Statement::StackOperation(o) => return self.stack_operation(o),
+            Statement::Bind(ident) => self.bind(ident, false)?,
+            Statement::Const(ident) => self.bind(ident, true)?,
+            Statement::Unbind(ident) => self.unbind(ident)?,
+            Statement::Defined(ident) => self.defined(ident)?,
+            Statement::If(conditions, statements, elifs, else_statements) => {
+                return self.interpret_if(conditions, statements, elifs, else_statements)
             }
-            Statement::SingleOperation(o) => {
-                let action = match o {
-                    SingleOpType::Print => "Printing",
-                    SingleOpType::Dup => "Duping",
-                    SingleOpType::Drop => "Dropping",
-                };
+            Statement::Cond(clauses, else_statements) => {
+                return self.interpret_clauses("Cond", clauses, else_statements)
+            }
+            Statement::While(conditions, statements) => {
+                return self.interpret_while(conditions, statements)
+            }
+            Statement::For(statements) => return self.interpret_for(statements),
+            Statement::Repeat(count, statements) => {
+                return self.interpret_repeat(count, statements)
+            }
+            Statement::Apply => return self.apply(),
+            Statement::Times => return self.times(),
+            Statement::Procedure(ident, _, statements) => self.define_proc(ident, statements)?,
+            Statement::Call(ident) => return self.call_proc(ident),
+            Statement::Break => return Ok(Flow::Break),
+            Statement::Continue => return Ok(Flow::Continue),
+            Statement::Empty => (),
+        }
 
-                let val = if let Some(val) = self.stack.pop() {
-                    val
-                } else {
-                    return Err(ConstantError::InvalidStackAmount(action.into(), 1));
-                };
+        Ok(Flow::Normal)
+    }
 
-                match o {
-                    SingleOpType::Print => println!("{val}"),
-                    SingleOpType::Dup => {
-                        self.stack.push(val.clone());
-                        self.stack.push(val);
+    // blocks run in the current scope, so a 'break' inside one can leave a loop
+    fn apply(&mut self) -> Result<Flow, ConstantError> {
+        match self.stack.pop() {
+            Some(Literal::Block(statements)) => self.interpret_block(&statements),
+            Some(val) => {
+                self.stack.push(val);
+                Err(ConstantError::InvalidOperation(
+                    "Apply expects a block on top of stack".into(),
+                ))
+            }
+            None => Err(ConstantError::InvalidStackAmount("Applying".into(), 1)),
+        }
+    }
+
+    // runs the block on top the number of times below it, like a repeat loop
+    fn times(&mut self) -> Result<Flow, ConstantError> {
+        if self.stack.len() < 2 {
+            return Err(ConstantError::InvalidStackAmount(
+                "Repeating a block".into(),
+                2,
+            ));
+        }
+
+        let block = self.stack.pop().unwrap();
+        let count = self.stack.pop().unwrap();
+        match (count, block) {
+            (Literal::Int(n), Literal::Block(statements)) if n >= 0 => {
+                for _ in 0..n {
+                    if self.interpret_block(&statements)? == Flow::Break {
+                        break;
                     }
-                    SingleOpType::Drop => (),
                 }
+
+                Ok(Flow::Normal)
             }
-            Statement::DoubleOperation(o) => {
-                let action = match o {
-                    DoubleOpType::Add => "Addition",
-                    DoubleOpType::Sub => "Subtraction",
-                    DoubleOpType::Mul => "Multiplication",
-                    DoubleOpType::Div => "Division",
-                    DoubleOpType::Swap => "Swapping",
-                    DoubleOpType::Mod => "Modulo",
-                    _ => "Comparison",
-                };
+            (count, block) => {
+                self.stack.push(count);
+                self.stack.push(block);
+                Err(ConstantError::InvalidOperation(
+                    "Times expects a non-negative integer count below a block".into(),
+                ))
+            }
+        }
+    }
 
-                if self.stack.len() < 2 {
-                    return Err(ConstantError::InvalidStackAmount(action.into(), 2));
+    fn push_value(&mut self, value: &Value) -> Result<(), ConstantError> {
+        match value {
+            Value::Literal(l) => self.push(l.clone()),
+            Value::Block(b) => self.push(Literal::Block(b.clone())),
+            Value::Ident(i) => {
+                if let Some(v) = self.scopes.iter().rev().find_map(|scope| scope.get(i)) {
+                    let v = v.clone();
+                    self.push(v)
+                } else {
+                    Err(ConstantError::IdentDoesNotExist(i.into()))
                 }
+            }
+        }
+    }
 
-                // these can just be unwrapped thanks to the line above
-                let second = self.stack.pop().unwrap();
-                let first = self.stack.pop().unwrap();
+    fn bind(&mut self, ident: &str, constant: bool) -> Result<(), ConstantError> {
+        let val = if let Some(val) = self.stack.pop() {
+            val
+        } else {
+            return Err(ConstantError::InvalidStackAmount("Binding".into(), 1));
+        };
 
-                if *o == DoubleOpType::Swap {
-                    self.stack.push(second.clone());
-                }
+        // bindings always go in the innermost scope, where a
+        // constant can shadow an outer one but not be rebound
+        let constants = self.constants.last_mut().unwrap();
+        if constants.contains(ident) {
+            self.stack.push(val);
+            return Err(ConstantError::ConstantReassignment(ident.into()));
+        }
+        if constant {
+            constants.insert(ident.into());
+        }
+        self.scopes.last_mut().unwrap().insert(ident.into(), val);
 
-                let res = |x: Literal, y: Literal| match o {
-                    DoubleOpType::Add => x + y,
-                    DoubleOpType::Sub => x - y,
-                    DoubleOpType::Mul => x * y,
-                    DoubleOpType::Div => x / y,
-                    DoubleOpType::Mod => x % y,
-                    DoubleOpType::Swap => Ok(x),
-                    DoubleOpType::GT => Ok(Literal::Bool(x > y)),
-                    DoubleOpType::GTEq => Ok(Literal::Bool(x >= y)),
-                    DoubleOpType::LT => Ok(Literal::Bool(x < y)),
-                    DoubleOpType::LTEq => Ok(Literal::Bool(x <= y)),
-                    DoubleOpType::Eq => Ok(Literal::Bool(x == y)),
-                    DoubleOpType::NotEq => Ok(Literal::Bool(x != y)),
-                    DoubleOpType::And | DoubleOpType::Or => match (x, y) {
-                        (Literal::Bool(a), Literal::Bool(b)) => match o {
-                            DoubleOpType::And => Ok(Literal::Bool(a && b)),
-                            DoubleOpType::Or => Ok(Literal::Bool(a || b)),
-                            _ => unreachable!(),
-                        },
-                        _ => {
-                            Err(ConstantError::InvalidOperation(
-                                "Logical operations can only be performed on bools".into(),
-                            ))
-                        }
-                    },
-                };
+        if self.procs.contains_key(ident) {
+            // ensures that "ident" is either a literal
+            // or a procedure but not both
+            self.procs.remove(ident);
+        }
 
-                match res(first.clone(), second.clone()) {
-                    Ok(v) => self.stack.push(v),
-                    Err(e) => {
-                        self.stack.push(first);
-                        self.stack.push(second);
-                        return Err(e);
+        Ok(())
+    }
+
+    // removes the innermost binding, so an outer one with the same name becomes visible
+    fn unbind(&mut self, ident: &str) -> Result<(), ConstantError> {
+        let i = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(ident))
+            .ok_or_else(|| ConstantError::IdentDoesNotExist(ident.into()))?;
+        if self.constants[i].contains(ident) {
+            return Err(ConstantError::ConstantReassignment(ident.into()));
+        }
+        self.scopes[i].remove(ident);
+
+        Ok(())
+    }
+
+    // names are defined when they're bound in any visible scope or are a procedure
+    fn defined(&mut self, ident: &str) -> Result<(), ConstantError> {
+        let defined = self.procs.contains_key(ident)
+            || self.scopes.iter().any(|scope| scope.contains_key(ident));
+        self.push(Literal::Bool(defined))
+    }
+
+    fn interpret_if(
+        &mut self,
+        conditions: &[Statement],
+        statements: &[Statement],
+        elifs: &[(Vec<Statement>, Vec<Statement>)],
+        else_statements: &[Statement],
+    ) -> Result<Flow, ConstantError> {
+        let flow = self.interpret_block(conditions)?;
+        if flow != Flow::Normal {
+            return Ok(flow);
+        }
+
+        let val = self.condition("If")?;
+
+        if val {
+            return self.interpret_block(statements);
+        }
+
+        self.interpret_clauses("If", elifs, else_statements)
+    }
+
+    // runs the body of the first clause whose conditions are true, or the else body,
+    // the statement's name is used in errors about the conditions
+    fn interpret_clauses(
+        &mut self,
+        statement: &str,
+        clauses: &[(Vec<Statement>, Vec<Statement>)],
+        else_statements: &[Statement],
+    ) -> Result<Flow, ConstantError> {
+        for (conditions, statements) in clauses {
+            let flow = self.interpret_block(conditions)?;
+            if flow != Flow::Normal {
+                return Ok(flow);
+            }
+
+            let val = self.condition(statement)?;
+
+            if val {
+                return self.interpret_block(statements);
+            }
+        }
+
+        self.interpret_block(else_statements)
+    }
+
+    fn interpret_while(
+        &mut self,
+        conditions: &[Statement],
+        statements: &[Statement],
+    ) -> Result<Flow, ConstantError> {
+        loop {
+            let flow = match self.interpret_block(conditions)? {
+                Flow::Normal => {
+                    let val = self.condition("While")?;
+
+                    if val {
+                        self.interpret_block(statements)?
+                    } else {
+                        break;
                     }
                 }
+                flow => flow,
+            };
+
+            if flow == Flow::Break {
+                break;
             }
-            Statement::Bind(ident) => {
-                let val = if let Some(val) = self.stack.pop() {
-                    val
-                } else {
-                    return Err(ConstantError::InvalidStackAmount("Binding".into(), 1));
-                };
+        }
 
-                self.idents.insert(ident.into(), val);
+        Ok(Flow::Normal)
+    }
 
-                if self.procs.contains_key(ident) {
-                    // ensures that "ident" is either a literal
-                    // or a procedure but not both
-                    self.procs.remove(ident);
-                }
+    fn interpret_for(&mut self, statements: &[Statement]) -> Result<Flow, ConstantError> {
+        if self.stack.len() < 2 {
+            return Err(ConstantError::InvalidStackAmount("For loop".into(), 2));
+        }
+
+        // the range is taken from the stack as 'start end'
+        let end = self.stack.pop().unwrap();
+        let start = self.stack.pop().unwrap();
+
+        let (Literal::Int(from), Literal::Int(to)) = (&start, &end) else {
+            self.stack.push(start);
+            self.stack.push(end);
+            return Err(ConstantError::InvalidOperation(
+                "For loop expects integer bounds on top of stack".into(),
+            ));
+        };
+
+        for i in *from..*to {
+            self.push(Literal::Int(i))?;
+            if self.interpret_block(statements)? == Flow::Break {
+                break;
             }
-            Statement::If(conditions, statements, elifs, else_statements) => {
-                for statement in conditions {
-                    self.interpret_statement(statement)?;
-                }
+        }
 
-                let val = if let Some(Literal::Bool(b)) = self.stack.pop() {
-                    b
-                } else {
-                    return Err(ConstantError::InvalidOperation(
-                        "If statement expects boolean value on top of stack".into(),
-                    ));
-                };
+        Ok(Flow::Normal)
+    }
 
-                if val {
-                    for statement in statements {
-                        self.interpret_statement(statement)?;
-                    }
-                } else {
-                    let mut do_else = true;
-                    for (elif_conditions, elif_statements) in elifs {
-                        for elif_condition in elif_conditions {
-                            self.interpret_statement(elif_condition)?;
-                        }
-                        let val = if let Some(Literal::Bool(b)) = self.stack.pop() {
-                            b
-                        } else {
-                            return Err(ConstantError::InvalidOperation(
-                                "If statement expects boolean value on top of stack".into(),
-                            ));
-                        };
+    fn interpret_repeat(
+        &mut self,
+        count: &[Statement],
+        statements: &[Statement],
+    ) -> Result<Flow, ConstantError> {
+        let flow = self.interpret_block(count)?;
+        if flow != Flow::Normal {
+            return Ok(flow);
+        }
 
-                        if val {
-                            do_else = false;
-                            for elif_statement in elif_statements {
-                                self.interpret_statement(elif_statement)?;
-                            }
-                            break;
+        let times = match self.stack.pop() {
+            Some(Literal::Int(n)) if n >= 0 => n,
+            Some(val) => {
+                self.stack.push(val);
+                return Err(ConstantError::InvalidOperation(
+                    "Repeat expects a non-negative integer count on top of stack".into(),
+                ));
+            }
+            None => return Err(ConstantError::InvalidStackAmount("Repeat".into(), 1)),
+        };
+
+        for _ in 0..times {
+            if self.interpret_block(statements)? == Flow::Break {
+                break;
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn define_proc(&mut self, ident: &str, statements: &[Statement]) -> Result<(), ConstantError> {
+        // reaching a registered definition for the first time isn't a redefinition
+        let registered = self.pending_procs.remove(ident);
+        if self.strict && !registered && self.procs.contains_key(ident) {
+            return Err(ConstantError::ProcAlreadyExists(ident.into()));
+        }
+
+        if self.constants.iter().any(|c| c.contains(ident)) {
+            return Err(ConstantError::ConstantReassignment(ident.into()));
+        }
+
+        self.procs.insert(ident.into(), statements.to_vec());
+
+        for scope in &mut self.scopes {
+            scope.remove(ident);
+        }
+
+        Ok(())
+    }
+
+    fn call_proc(&mut self, ident: &str) -> Result<Flow, ConstantError> {
+        let Some(statements) = self.procs.get(ident).cloned() else {
+            return Err(ConstantError::ProcDoesNotExist(ident.into()));
+        };
+
+        // each call gets its own scope for bindings
+        self.scopes.push(HashMap::new());
+        self.constants.push(HashSet::new());
+        let res = self.interpret_block(&statements);
+        self.constants.pop();
+        self.scopes.pop();
+
+        // loops can't be broken out of from inside a procedure
+        match res? {
+            Flow::Break => Err(ConstantError::OutsideLoop("break".into())),
+            Flow::Continue => Err(ConstantError::OutsideLoop("continue".into())),
+            Flow::Normal => Ok(Flow::Normal),
+        }
+    }
+
+    fn single_operation(&mut self, o: &SingleOpType) -> Result<Flow, ConstantError> {
+        let action = match o {
+            SingleOpType::Print => "Printing",
+            SingleOpType::Dup => "Duping",
+            SingleOpType::Drop => "Dropping",
+            SingleOpType::Not => "Negating",
+            SingleOpType::Arg => "Getting an argument",
+            SingleOpType::ToNumber => "Converting to a number",
+            SingleOpType::ToString => "Converting to a string",
+            SingleOpType::Len => "Getting the length",
+            SingleOpType::Write => "Writing",
+            SingleOpType::Pick => "Picking",
+            SingleOpType::Sqrt => "Square rooting",
+            SingleOpType::Abs => "Getting the absolute value",
+            SingleOpType::Floor => "Flooring",
+            SingleOpType::Ceil => "Ceiling",
+            SingleOpType::Round => "Rounding",
+            SingleOpType::Neg => "Negating a number",
+            SingleOpType::ToUpper => "Converting to uppercase",
+            SingleOpType::ToLower => "Converting to lowercase",
+            SingleOpType::ListLen => "Getting the length of a list",
+            SingleOpType::Assert => "Asserting",
+            SingleOpType::Exit => "Exiting",
+            SingleOpType::Ord => "Getting a code point",
+            SingleOpType::Chr => "Getting a char",
+            SingleOpType::Input => "Getting input",
+            SingleOpType::Roll => "Rolling",
+            SingleOpType::Trim => "Trimming",
+            SingleOpType::TrimStart => "Trimming",
+            SingleOpType::TrimEnd => "Trimming",
+            SingleOpType::Reverse => "Reversing",
+            SingleOpType::ToBool => "Converting to a boolean",
+            SingleOpType::ToInt => "Converting to an integer",
+            SingleOpType::Seed => "Seeding",
+        };
+
+        let val = if let Some(val) = self.stack.pop() {
+            val
+        } else {
+            return Err(ConstantError::InvalidStackAmount(action.into(), 1));
+        };
+
+        match o {
+            SingleOpType::Print => writeln!(self.output, "{val}")?,
+            SingleOpType::Write => write!(self.output, "{val}")?,
+            SingleOpType::Dup => {
+                self.stack.push(val.clone());
+                self.push(val)?;
+            }
+            SingleOpType::Drop => (),
+            SingleOpType::Exit => match val {
+                Literal::Int(code) if i32::try_from(code).is_ok() => {
+                    return Err(ConstantError::Exit(code as i32));
+                }
+                _ => {
+                    self.stack.push(val);
+                    return Err(ConstantError::InvalidOperation(
+                        "Exit expects an integer status code on top of stack".into(),
+                    ));
+                }
+            },
+            // the prompt stays on the same line as what's typed
+            SingleOpType::Input => match val {
+                Literal::String(prompt) => {
+                    write!(self.output, "{prompt}")?;
+                    self.output.flush()?;
+                    match self.read_line() {
+                        Ok(line) => self.push(Literal::String(line.trim().into()))?,
+                        Err(e) => {
+                            self.stack.push(Literal::String(prompt));
+                            return Err(e);
                         }
                     }
-                    if do_else {
-                        for else_statement in else_statements {
-                            self.interpret_statement(else_statement)?;
-                        }
+                }
+                _ => {
+                    self.stack.push(val);
+                    return Err(ConstantError::InvalidOperation(
+                        "Input expects a string prompt on top of stack".into(),
+                    ));
+                }
+            },
+            // the item n deep moves to the top, so 2 roll swaps and 3 roll rotates
+            SingleOpType::Roll => match val {
+                Literal::Int(n) if n >= 0 && self.stack.len() >= n as usize => {
+                    if n > 0 {
+                        let item = self.stack.remove(self.stack.len() - n as usize);
+                        self.stack.push(item);
                     }
                 }
-            }
-            Statement::While(conditions, statements) => loop {
-                for statement in conditions {
-                    self.interpret_statement(statement)?;
+                Literal::Int(n) if n >= 0 => {
+                    self.stack.push(val);
+                    return Err(ConstantError::InvalidStackAmount(
+                        action.into(),
+                        n as usize + 1,
+                    ));
                 }
-                let val = if let Some(Literal::Bool(b)) = self.stack.pop() {
-                    b
-                } else {
+                _ => {
+                    self.stack.push(val);
                     return Err(ConstantError::InvalidOperation(
-                        "While statement expects boolean value on top of stack".into(),
+                        "Roll expects a non-negative integer on top of stack".into(),
                     ));
+                }
+            },
+            // the seed is mixed with splitmix64 so that nearby seeds
+            // don't start out giving nearby numbers
+            SingleOpType::Seed => {
+                let seed = match val {
+                    Literal::Int(n) => n as u64,
+                    Literal::Number(n) => n.to_bits() as u64,
+                    _ => {
+                        self.stack.push(val);
+                        return Err(ConstantError::InvalidOperation(
+                            "Seed expects a number on top of stack".into(),
+                        ));
+                    }
                 };
 
-                if val {
-                    for statement in statements {
-                        self.interpret_statement(statement)?;
-                    }
-                } else {
-                    break;
+                let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^= z >> 31;
+                self.rng = if z == 0 { DEFAULT_SEED } else { z };
+            }
+            SingleOpType::Assert => match val {
+                Literal::Bool(true) => (),
+                Literal::Bool(false) => return Err(ConstantError::AssertionFailed),
+                _ => {
+                    self.stack.push(val);
+                    return Err(ConstantError::InvalidOperation(
+                        "Assert expects a boolean on top of stack".into(),
+                    ));
                 }
             },
-            Statement::Procedure(ident, statements) => {
-                self.procs.insert(ident.into(), statements.to_vec());
+            _ => {
+                let res = |x: Literal| match o {
+                    SingleOpType::Not => match x {
+                        Literal::Bool(b) => Ok(Literal::Bool(!b)),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only negate booleans".into(),
+                        )),
+                    },
+                    SingleOpType::Arg => match x {
+                        Literal::Int(i) if i >= 0 && (i as usize) < self.args.len() => {
+                            Ok(Literal::String(self.args[i as usize].clone()))
+                        }
+                        _ if self.args.is_empty() => Err(ConstantError::InvalidOperation(
+                            "No arguments were passed to the program".into(),
+                        )),
+                        _ => Err(ConstantError::InvalidOperation(format!(
+                            "Argument index must be an integer below {}",
+                            self.args.len()
+                        ))),
+                    },
+                    SingleOpType::ToNumber => match x {
+                        Literal::String(s) => {
+                            if let Ok(n) = s.parse::<i64>() {
+                                Ok(Literal::Int(n))
+                            } else if let Ok(n) = s.parse::<f32>() {
+                                Ok(Literal::Number(n))
+                            } else {
+                                Err(ConstantError::InvalidOperation(format!(
+                                    "Could not convert '{s}' to a number"
+                                )))
+                            }
+                        }
+                        Literal::Bool(b) => Ok(Literal::Int(b as i64)),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only convert strings and booleans to numbers".into(),
+                        )),
+                    },
+                    // floats are truncated toward zero
+                    SingleOpType::ToInt => match x {
+                        Literal::Int(n) => Ok(Literal::Int(n)),
+                        Literal::Number(n)
+                            if n.is_finite()
+                                && n.trunc() >= i64::MIN as f32
+                                && n.trunc() < i64::MAX as f32 =>
+                        {
+                            Ok(Literal::Int(n as i64))
+                        }
+                        Literal::Number(n) => Err(ConstantError::InvalidOperation(format!(
+                            "{n} does not fit in an integer"
+                        ))),
+                        Literal::Bool(b) => Ok(Literal::Int(b as i64)),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only convert numbers and booleans to integers".into(),
+                        )),
+                    },
+                    // any number other than zero is true, and only the
+                    // exact strings "true" and "false" can be converted
+                    SingleOpType::ToBool => match x {
+                        Literal::Bool(b) => Ok(Literal::Bool(b)),
+                        Literal::Int(n) => Ok(Literal::Bool(n != 0)),
+                        Literal::Number(n) => Ok(Literal::Bool(n != 0.0)),
+                        Literal::String(s) => match s.as_str() {
+                            "true" => Ok(Literal::Bool(true)),
+                            "false" => Ok(Literal::Bool(false)),
+                            _ => Err(ConstantError::InvalidOperation(format!(
+                                "Could not convert '{s}' to a boolean"
+                            ))),
+                        },
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only convert numbers and strings to booleans".into(),
+                        )),
+                    },
+                    SingleOpType::ToString => Ok(Literal::String(x.to_string())),
+                    // counts characters rather than bytes
+                    SingleOpType::Len => match x {
+                        Literal::String(s) => Ok(Literal::Int(s.chars().count() as i64)),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only get the length of strings".into(),
+                        )),
+                    },
+                    SingleOpType::ToUpper | SingleOpType::ToLower => match x {
+                        Literal::String(s) if *o == SingleOpType::ToUpper => {
+                            Ok(Literal::String(s.to_uppercase()))
+                        }
+                        Literal::String(s) => Ok(Literal::String(s.to_lowercase())),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only change the case of strings".into(),
+                        )),
+                    },
+                    SingleOpType::Trim | SingleOpType::TrimStart | SingleOpType::TrimEnd => match x
+                    {
+                        Literal::String(s) => Ok(Literal::String(
+                            match o {
+                                SingleOpType::TrimStart => s.trim_start(),
+                                SingleOpType::TrimEnd => s.trim_end(),
+                                _ => s.trim(),
+                            }
+                            .into(),
+                        )),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only trim strings".into(),
+                        )),
+                    },
+                    // reverses code points, so combining marks end up on the wrong character
+                    SingleOpType::Reverse => match x {
+                        Literal::String(s) => Ok(Literal::String(s.chars().rev().collect())),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only reverse strings".into(),
+                        )),
+                    },
+                    // one character strings work as well as chars
+                    SingleOpType::Ord => match x {
+                        Literal::Char(c) => Ok(Literal::Int(c as i64)),
+                        Literal::String(s) if s.chars().count() == 1 => {
+                            Ok(Literal::Int(s.chars().next().unwrap() as i64))
+                        }
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Ord expects a char or a one character string".into(),
+                        )),
+                    },
+                    SingleOpType::Chr => match x {
+                        Literal::Int(n) => u32::try_from(n)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .map(Literal::Char)
+                            .ok_or_else(|| {
+                                ConstantError::InvalidOperation(format!(
+                                    "{n} is not a valid code point"
+                                ))
+                            }),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Chr expects an integer code point".into(),
+                        )),
+                    },
+                    SingleOpType::ListLen => match x {
+                        Literal::List(l) => Ok(Literal::Int(l.len() as i64)),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only get the length of lists".into(),
+                        )),
+                    },
+                    // copies the item n below the top, so '0 pick' is the same as 'dup'
+                    SingleOpType::Pick => match x {
+                        Literal::Int(n) if n >= 0 => self
+                            .stack
+                            .iter()
+                            .rev()
+                            .nth(n as usize)
+                            .cloned()
+                            .ok_or_else(|| {
+                                ConstantError::InvalidStackAmount(action.into(), n as usize + 2)
+                            }),
+                        // no stack is deep enough for a negative or fractional index,
+                        // so it's reported against the smallest possible pick
+                        _ => Err(ConstantError::InvalidStackAmount(action.into(), 2)),
+                    },
+                    SingleOpType::Sqrt => match x.as_number() {
+                        Some(n) if n >= 0.0 => Ok(Literal::Number(n.sqrt())),
+                        Some(_) => Err(ConstantError::InvalidOperation(
+                            "Cannot take the square root of a negative number".into(),
+                        )),
+                        None => Err(ConstantError::InvalidOperation(
+                            "Can only take the square root of numbers".into(),
+                        )),
+                    },
+                    SingleOpType::Abs => match x {
+                        Literal::Int(n) => n.checked_abs().map(Literal::Int).ok_or_else(|| {
+                            ConstantError::InvalidOperation("Integer overflow".into())
+                        }),
+                        Literal::Number(n) => Ok(Literal::Number(n.abs())),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only take the absolute value of numbers".into(),
+                        )),
+                    },
+                    SingleOpType::Neg => match x {
+                        Literal::Int(n) => n.checked_neg().map(Literal::Int).ok_or_else(|| {
+                            ConstantError::InvalidOperation("Integer overflow".into())
+                        }),
+                        Literal::Number(n) => Ok(Literal::Number(-n)),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only negate numbers".into(),
+                        )),
+                    },
+                    // integers are already whole so they're left as they are
+                    SingleOpType::Floor | SingleOpType::Ceil | SingleOpType::Round => match x {
+                        Literal::Int(n) => Ok(Literal::Int(n)),
+                        Literal::Number(n) => Ok(Literal::Number(match o {
+                            SingleOpType::Floor => n.floor(),
+                            SingleOpType::Ceil => n.ceil(),
+                            _ => n.round(),
+                        })),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only round numbers".into(),
+                        )),
+                    },
+                    _ => unreachable!(),
+                };
 
-                if self.idents.contains_key(ident) {
-                    self.idents.remove(ident);
-                }
-            }
-            Statement::Call(ident) => {
-                if let Some(statements) = self.procs.get(ident).cloned() {
-                    for statement in statements {
-                        self.interpret_statement(&statement)?;
+                match res(val.clone()) {
+                    Ok(v) => self.stack.push(v),
+                    Err(e) => {
+                        self.stack.push(val);
+                        return Err(e);
                     }
-                } else {
-                    return Err(ConstantError::ProcDoesNotExist(ident.into()));
                 }
             }
-            Statement::Empty => (),
         }
 
-        Ok(())
+        Ok(Flow::Normal)
     }
 
-    pub fn repl(&mut self) {
-        println!("Welcome to the Constant REPL, type 'exit' or 'quit' to quit");
-        loop {
-            print!("> ");
-            std::io::stdout()
-                .flush()
-                .expect("Error: Could not flush stdout");
-
-            let mut code = String::new();
-            std::io::stdin()
-                .read_line(&mut code)
-                .expect("Error: Could not read input");
-
-            if code.trim() == "exit" || code.trim() == "quit" {
-                return;
-            }
+    fn double_operation(&mut self, o: &DoubleOpType) -> Result<Flow, ConstantError> {
+        let action = match o {
+            DoubleOpType::Add => "Addition",
+            DoubleOpType::Sub => "Subtraction",
+            DoubleOpType::Mul => "Multiplication",
+            DoubleOpType::Div => "Division",
+            DoubleOpType::Swap => "Swapping",
+            DoubleOpType::Over => "Over",
+            DoubleOpType::Mod => "Modulo",
+            DoubleOpType::BAnd => "Bitwise and",
+            DoubleOpType::BOr => "Bitwise or",
+            DoubleOpType::BXor => "Bitwise xor",
+            DoubleOpType::Shl => "Shifting left",
+            DoubleOpType::Shr => "Shifting right",
+            DoubleOpType::Min => "Minimum",
+            DoubleOpType::Max => "Maximum",
+            DoubleOpType::Pow => "Exponentiation",
+            DoubleOpType::CharAt => "Getting a character",
+            DoubleOpType::ListPush => "Pushing to a list",
+            DoubleOpType::TwoDup => "Duping a pair",
+            DoubleOpType::TwoDrop => "Dropping a pair",
+            DoubleOpType::Nip => "Nipping",
+            DoubleOpType::Tuck => "Tucking",
+            DoubleOpType::IDiv => "Integer division",
+            DoubleOpType::Contains => "Searching a string",
+            DoubleOpType::StartsWith => "Checking a prefix",
+            DoubleOpType::EndsWith => "Checking a suffix",
+            _ => "Comparison",
+        };
 
-            let tokens = match Lexer::new(&code).tokenize() {
-                Ok(tokens) => tokens,
-                Err(e) => {
-                    println!("{e}");
-                    continue;
-                }
-            };
-            let ast = match Parser::new(&tokens).parse() {
-                Ok(a) => a,
-                Err(e) => {
-                    println!("{e}");
-                    continue;
-                }
-            };
+        if self.stack.len() < 2 {
+            return Err(ConstantError::InvalidStackAmount(action.into(), 2));
+        }
 
-            self.program = ast;
+        // these can just be unwrapped thanks to the line above
+        let second = self.stack.pop().unwrap();
+        let first = self.stack.pop().unwrap();
 
-            if let Err(e) = self.interpret() {
-                println!("{e}");
+        match o {
+            DoubleOpType::Swap => {
+                self.stack.push(second);
+                self.stack.push(first);
             }
-        }
+            DoubleOpType::Over => {
+                self.stack.push(first.clone());
+                self.stack.push(second);
+                self.push(first)?;
+            }
+            DoubleOpType::TwoDup => {
+                self.stack.push(first.clone());
+                self.stack.push(second.clone());
+                self.push(first)?;
+                self.push(second)?;
+            }
+            DoubleOpType::TwoDrop => (),
+            DoubleOpType::Nip => self.stack.push(second),
+            DoubleOpType::Tuck => {
+                self.stack.push(second.clone());
+                self.stack.push(first);
+                self.push(second)?;
+            }
+            _ => {
+                let res = |x: Literal, y: Literal| match o {
+                    DoubleOpType::Add => x + y,
+                    DoubleOpType::Sub => x - y,
+                    DoubleOpType::Mul => x * y,
+                    DoubleOpType::Div => x / y,
+                    DoubleOpType::Mod => x % y,
+                    DoubleOpType::GT
+                    | DoubleOpType::GTEq
+                    | DoubleOpType::LT
+                    | DoubleOpType::LTEq
+                    | DoubleOpType::Eq
+                    | DoubleOpType::NotEq => {
+                        let ord = x.compare(&y);
+                        // values of the same type that can't be ordered, like NaN, just
+                        // compare as false, but different types are always a mistake
+                        if ord.is_none() && x.type_name() != y.type_name() {
+                            return Err(ConstantError::InvalidOperation(format!(
+                                "Cannot compare {} with {}",
+                                x.type_name(),
+                                y.type_name()
+                            )));
+                        }
+                        if matches!(x, Literal::List(_) | Literal::Block(_)) {
+                            return Err(ConstantError::InvalidOperation(format!(
+                                "Cannot compare {}s",
+                                x.type_name()
+                            )));
+                        }
+                        // booleans can be checked for equality but have no order
+                        if matches!(x, Literal::Bool(_))
+                            && !matches!(o, DoubleOpType::Eq | DoubleOpType::NotEq)
+                        {
+                            return Err(ConstantError::InvalidOperation(
+                                "Cannot order booleans".into(),
+                            ));
+                        }
+                        Ok(Literal::Bool(match o {
+                            DoubleOpType::GT => ord == Some(Greater),
+                            DoubleOpType::GTEq => matches!(ord, Some(Greater | Equal)),
+                            DoubleOpType::LT => ord == Some(Less),
+                            DoubleOpType::LTEq => matches!(ord, Some(Less | Equal)),
+                            DoubleOpType::Eq => ord == Some(Equal),
+                            _ => ord != Some(Equal),
+                        }))
+                    }
+                    DoubleOpType::And | DoubleOpType::Or => match (x, y) {
+                        (Literal::Bool(a), Literal::Bool(b)) => match o {
+                            DoubleOpType::And => Ok(Literal::Bool(a && b)),
+                            DoubleOpType::Or => Ok(Literal::Bool(a || b)),
+                            _ => unreachable!(),
+                        },
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Logical operations can only be performed on bools".into(),
+                        )),
+                    },
+                    // rounds towards zero, so '-7 2 idiv' is -3
+                    DoubleOpType::IDiv => match (x.as_number(), y.as_number()) {
+                        (Some(_), Some(0.0)) => {
+                            Err(ConstantError::InvalidOperation("Division by zero".into()))
+                        }
+                        (Some(n), Some(m)) => match (x, y) {
+                            (Literal::Int(a), Literal::Int(b)) => {
+                                a.checked_div(b).map(Literal::Int).ok_or_else(|| {
+                                    ConstantError::InvalidOperation("Integer overflow".into())
+                                })
+                            }
+                            _ => Ok(Literal::Number((n / m).trunc())),
+                        },
+                        _ => Err(x.mismatch("divide", &y)),
+                    },
+                    // the exponent is on top with the base below it, '0 0 pow' is 1
+                    DoubleOpType::Pow => match (x, y) {
+                        (Literal::Int(a), Literal::Int(b)) if b >= 0 => u32::try_from(b)
+                            .ok()
+                            .and_then(|b| a.checked_pow(b))
+                            .map(Literal::Int)
+                            .ok_or_else(|| {
+                                ConstantError::InvalidOperation("Integer overflow".into())
+                            }),
+                        (x, y) => match (x.as_number(), y.as_number()) {
+                            (Some(a), Some(b)) if a.powf(b).is_nan() => {
+                                Err(ConstantError::InvalidOperation(
+                                    "Exponentiation did not produce a real number".into(),
+                                ))
+                            }
+                            (Some(a), Some(b)) => Ok(Literal::Number(a.powf(b))),
+                            _ => Err(ConstantError::InvalidOperation(
+                                "Can only raise numbers to a power".into(),
+                            )),
+                        },
+                    },
+                    DoubleOpType::ListPush => match x {
+                        Literal::List(mut l) => {
+                            l.push(y);
+                            Ok(Literal::List(l))
+                        }
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Can only push values onto lists".into(),
+                        )),
+                    },
+                    // every string contains the empty string
+                    DoubleOpType::Contains => match (x, y) {
+                        (Literal::String(s), Literal::String(needle)) => {
+                            Ok(Literal::Bool(s.contains(needle.as_str())))
+                        }
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Contains expects two strings".into(),
+                        )),
+                    },
+                    DoubleOpType::StartsWith => match (x, y) {
+                        (Literal::String(s), Literal::String(prefix)) => {
+                            Ok(Literal::Bool(s.starts_with(prefix.as_str())))
+                        }
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Starts_with expects two strings".into(),
+                        )),
+                    },
+                    DoubleOpType::EndsWith => match (x, y) {
+                        (Literal::String(s), Literal::String(suffix)) => {
+                            Ok(Literal::Bool(s.ends_with(suffix.as_str())))
+                        }
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Ends_with expects two strings".into(),
+                        )),
+                    },
+                    // indexes by character rather than byte
+                    DoubleOpType::CharAt => match (x, y) {
+                        (Literal::String(s), Literal::Int(i)) => usize::try_from(i)
+                            .ok()
+                            .and_then(|i| s.chars().nth(i))
+                            .map(|c| Literal::String(c.into()))
+                            .ok_or_else(|| {
+                                ConstantError::InvalidOperation(format!(
+                                    "Index {i} is out of bounds for a string of length {}",
+                                    s.chars().count()
+                                ))
+                            }),
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Char_at expects a string and an integer index".into(),
+                        )),
+                    },
+                    // equal values keep the lower one, NaN can't be ordered so it errors
+                    DoubleOpType::Min | DoubleOpType::Max => {
+                        if x.as_number().is_none() || y.as_number().is_none() {
+                            return Err(ConstantError::InvalidOperation(
+                                "Can only get the minimum or maximum of numbers".into(),
+                            ));
+                        }
+                        match (x.compare(&y), o) {
+                            (Some(Equal), _) => Ok(x),
+                            (Some(Less), DoubleOpType::Min)
+                            | (Some(Greater), DoubleOpType::Max) => Ok(x),
+                            (Some(_), _) => Ok(y),
+                            (None, _) => Err(ConstantError::InvalidOperation(
+                                "Cannot get the minimum or maximum of NaN".into(),
+                            )),
+                        }
+                    }
+                    DoubleOpType::Swap
+                    | DoubleOpType::Over
+                    | DoubleOpType::TwoDup
+                    | DoubleOpType::TwoDrop
+                    | DoubleOpType::Nip
+                    | DoubleOpType::Tuck => unreachable!(),
+                    _ => match (x, y) {
+                        (Literal::Int(a), Literal::Int(b)) => match o {
+                            DoubleOpType::BAnd => Ok(Literal::Int(a & b)),
+                            DoubleOpType::BOr => Ok(Literal::Int(a | b)),
+                            DoubleOpType::BXor => Ok(Literal::Int(a ^ b)),
+                            // shifting by the width of an i64 or more is rejected
+                            // rather than wrapping the shift amount around
+                            _ => match u32::try_from(b).ok().filter(|&b| b < i64::BITS) {
+                                Some(b) if *o == DoubleOpType::Shl => Ok(Literal::Int(a << b)),
+                                Some(b) => Ok(Literal::Int(a >> b)),
+                                None => Err(ConstantError::InvalidOperation(
+                                    "Shift amount must be between 0 and 63".into(),
+                                )),
+                            },
+                        },
+                        _ => Err(ConstantError::InvalidOperation(
+                            "Bitwise operations can only be performed on integers".into(),
+                        )),
+                    },
+                };
+
+                match res(first.clone(), second.clone()) {
+                    Ok(v) => self.push(v)?,
+                    Err(e) => {
+                        self.stack.push(first);
+                        self.stack.push(second);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn triple_operation(&mut self, o: &TripleOpType) -> Result<Flow, ConstantError> {
+        let action = match o {
+            TripleOpType::Rot => "Rotating",
+            TripleOpType::Select => "Selecting",
+            TripleOpType::Substring => "Getting a substring",
+            TripleOpType::ReverseRot => "Reverse rotating",
+            TripleOpType::Replace => "Replacing",
+        };
+
+        if self.stack.len() < 3 {
+            return Err(ConstantError::InvalidStackAmount(action.into(), 3));
+        }
+
+        // these can just be unwrapped thanks to the line above
+        let third = self.stack.pop().unwrap();
+        let second = self.stack.pop().unwrap();
+        let first = self.stack.pop().unwrap();
+
+        match o {
+            TripleOpType::Rot => {
+                self.stack.push(second);
+                self.stack.push(third);
+                self.stack.push(first);
+            }
+            TripleOpType::ReverseRot => {
+                self.stack.push(third);
+                self.stack.push(first);
+                self.stack.push(second);
+            }
+            _ => {
+                let res = |x: Literal, y: Literal, z: Literal| {
+                    match o {
+                        TripleOpType::Select => match x {
+                            Literal::Bool(b) => Ok(if b { y } else { z }),
+                            _ => Err(ConstantError::InvalidOperation(
+                                "Select expects a boolean below the two values".into(),
+                            )),
+                        },
+                        // takes the string, then the start, then the length on top,
+                        // all counted in characters rather than bytes
+                        TripleOpType::Substring => match (x, y, z) {
+                            (Literal::String(s), Literal::Int(start), Literal::Int(len))
+                                if start >= 0
+                                    && len >= 0
+                                    && start.saturating_add(len) <= s.chars().count() as i64 =>
+                            {
+                                Ok(Literal::String(
+                                    s.chars().skip(start as usize).take(len as usize).collect(),
+                                ))
+                            }
+                            (Literal::String(_), Literal::Int(start), Literal::Int(len)) => {
+                                Err(ConstantError::InvalidOperation(format!(
+                                    "Substring of length {len} starting at {start} is out of bounds"
+                                )))
+                            }
+                            _ => Err(ConstantError::InvalidOperation(
+                                "Substring expects a string, a start index, and a length".into(),
+                            )),
+                        },
+                        // takes the string, then the pattern, then the replacement on top,
+                        // an empty pattern matches nothing so the string is left as it is
+                        TripleOpType::Replace => match (x, y, z) {
+                            (Literal::String(s), Literal::String(from), Literal::String(_))
+                                if from.is_empty() =>
+                            {
+                                Ok(Literal::String(s))
+                            }
+                            (Literal::String(s), Literal::String(from), Literal::String(to)) => {
+                                Ok(Literal::String(s.replace(from.as_str(), &to)))
+                            }
+                            _ => Err(ConstantError::InvalidOperation(
+                                "Replace expects a string, a pattern, and a replacement".into(),
+                            )),
+                        },
+                        _ => unreachable!(),
+                    }
+                };
+
+                match res(first.clone(), second.clone(), third.clone()) {
+                    Ok(v) => self.stack.push(v),
+                    Err(e) => {
+                        self.stack.push(first);
+                        self.stack.push(second);
+                        self.stack.push(third);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn stack_operation(&mut self, o: &StackOpType) -> Result<Flow, ConstantError> {
+        match o {
+            StackOpType::Clear => self.stack.clear(),
+            StackOpType::Depth => {
+                // the depth doesn't include the value being pushed
+                let depth = self.stack.len() as i64;
+                self.push(Literal::Int(depth))?;
+            }
+            StackOpType::Dump => writeln!(self.output, "{}", self.format_stack())?,
+            StackOpType::ListNew => self.push(Literal::List(Vec::new()))?,
+            StackOpType::Rand => {
+                let n = self.next_random();
+                self.push(Literal::Number(n))?;
+            }
+            // looks at the top value without taking it off the stack
+            StackOpType::IsNumber | StackOpType::IsString | StackOpType::IsBool => {
+                let Some(top) = self.stack.last() else {
+                    return Err(ConstantError::InvalidStackAmount(
+                        "Checking a type".into(),
+                        1,
+                    ));
+                };
+
+                let is_type = match o {
+                    StackOpType::IsNumber => {
+                        matches!(top, Literal::Int(_) | Literal::Number(_))
+                    }
+                    StackOpType::IsString => matches!(top, Literal::String(_)),
+                    _ => matches!(top, Literal::Bool(_)),
+                };
+                self.push(Literal::Bool(is_type))?;
+            }
+            // copies are only made if they all fit, so the stack is never half duplicated
+            StackOpType::DupAll => {
+                if let Some(max) = self.max_stack {
+                    if self.stack.len() * 2 > max {
+                        return Err(ConstantError::StackOverflow(max));
+                    }
+                }
+
+                self.stack.extend_from_within(..);
+            }
+            // turns the whole stack like a queue, the bottom item comes up to the top
+            // or the top item goes down to the bottom
+            StackOpType::RollUp if !self.stack.is_empty() => self.stack.rotate_left(1),
+            StackOpType::RollDown if !self.stack.is_empty() => self.stack.rotate_right(1),
+            StackOpType::RollUp | StackOpType::RollDown => (),
+            // fills each '{}' in the string on top with the values below it,
+            // in order so that the last placeholder gets the value nearest the top
+            StackOpType::Format => {
+                let count = match self.stack.last() {
+                    Some(Literal::String(s)) => s.matches("{}").count(),
+                    Some(_) => {
+                        return Err(ConstantError::InvalidOperation(
+                            "Format expects a string on top of the stack".into(),
+                        ))
+                    }
+                    None => return Err(ConstantError::InvalidStackAmount("Formatting".into(), 1)),
+                };
+                if self.stack.len() < count + 1 {
+                    return Err(ConstantError::InvalidStackAmount(
+                        "Formatting".into(),
+                        count + 1,
+                    ));
+                }
+
+                let Some(Literal::String(s)) = self.stack.pop() else {
+                    unreachable!()
+                };
+                let values = self.stack.split_off(self.stack.len() - count);
+                let mut formatted = String::new();
+                for (i, piece) in s.split("{}").enumerate() {
+                    if i > 0 {
+                        formatted.push_str(&values[i - 1].to_string());
+                    }
+                    formatted.push_str(piece);
+                }
+                self.push(Literal::String(formatted))?;
+            }
+            StackOpType::Read => {
+                let line = self.read_line()?;
+                self.push(Literal::String(line))?;
+            }
+            // pushes each piece followed by how many pieces there were, an empty
+            // separator splits between every character and no match gives one piece
+            StackOpType::Split => {
+                if self.stack.len() < 2 {
+                    return Err(ConstantError::InvalidStackAmount("Splitting".into(), 2));
+                }
+
+                let sep = self.stack.pop().unwrap();
+                let source = self.stack.pop().unwrap();
+                let pieces = match (&source, &sep) {
+                    (Literal::String(s), Literal::String(sep)) if sep.is_empty() => {
+                        s.chars().map(String::from).collect::<Vec<String>>()
+                    }
+                    (Literal::String(s), Literal::String(sep)) => {
+                        s.split(sep.as_str()).map(String::from).collect()
+                    }
+                    _ => {
+                        self.stack.push(source);
+                        self.stack.push(sep);
+                        return Err(ConstantError::InvalidOperation(
+                            "Can only split strings by a string".into(),
+                        ));
+                    }
+                };
+
+                // the pieces and their count are only pushed if they all fit
+                if let Some(max) = self.max_stack {
+                    if self.stack.len() + pieces.len() + 1 > max {
+                        self.stack.push(source);
+                        self.stack.push(sep);
+                        return Err(ConstantError::StackOverflow(max));
+                    }
+                }
+
+                let count = pieces.len() as i64;
+                self.stack.extend(pieces.into_iter().map(Literal::String));
+                self.stack.push(Literal::Int(count));
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    // pushes a new value, as long as the stack has room for it
+    fn push(&mut self, val: Literal) -> Result<(), ConstantError> {
+        if let Some(max) = self.max_stack {
+            if self.stack.len() >= max {
+                return Err(ConstantError::StackOverflow(max));
+            }
+        }
+
+        self.stack.push(val);
+        Ok(())
+    }
+
+    // pops the boolean a condition left on the stack, anything else is
+    // left where it is so the stack isn't changed by the error
+    fn condition(&mut self, statement: &str) -> Result<bool, ConstantError> {
+        match self.stack.last() {
+            Some(Literal::Bool(b)) => {
+                let b = *b;
+                self.stack.pop();
+                Ok(b)
+            }
+            Some(val) => Err(ConstantError::InvalidOperation(format!(
+                "{statement} statement expects a boolean value on top of stack, found a {}",
+                val.type_name()
+            ))),
+            None => Err(ConstantError::InvalidOperation(format!(
+                "{statement} statement expects a boolean value but the stack is empty"
+            ))),
+        }
+    }
+
+    // renders the stack from the bottom to the top
+    fn format_stack(&self) -> String {
+        let items = self
+            .stack
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<String>>();
+
+        format!("[{}]", items.join(", "))
+    }
+
+    // makes top-level procedures callable before their definition is reached,
+    // names that are currently bound are left to be defined in order so that
+    // a name is never both a binding and a procedure
+    fn register_procs(&mut self, program: &[Statement]) {
+        self.pending_procs.clear();
+        for statement in program {
+            if let Statement::Procedure(ident, _, statements) = statement {
+                if !self.scopes.iter().any(|scope| scope.contains_key(ident)) {
+                    self.procs.insert(ident.into(), statements.to_vec());
+                    self.pending_procs.insert(ident.into());
+                }
+            }
+        }
+    }
+
+    fn trace_statement(&mut self, statement: &Statement) -> Result<(), ConstantError> {
+        let stack = self.format_stack();
+        if let Some(trace) = &mut self.trace {
+            writeln!(trace, "{statement:?} -> {stack}")?;
+        }
+
+        Ok(())
+    }
+
+    // runs statements until one of them breaks out of or continues a loop
+    fn interpret_block(&mut self, statements: &[Statement]) -> Result<Flow, ConstantError> {
+        // blocks run inside each other on the native stack, through procedure
+        // calls or just nesting, so deep recursion has to be stopped before it overflows
+        if self.depth >= self.recursion_limit {
+            return Err(ConstantError::RecursionLimitExceeded(self.recursion_limit));
+        }
+
+        self.depth += 1;
+        let mut res = Ok(Flow::Normal);
+        for statement in statements {
+            res = self.interpret_statement(statement);
+            if res.is_ok() && self.trace.is_some() {
+                if let Err(e) = self.trace_statement(statement) {
+                    res = Err(e);
+                }
+            }
+            if !matches!(res, Ok(Flow::Normal)) {
+                break;
+            }
+        }
+        self.depth -= 1;
+
+        res
+    }
+
+    // counts the blocks that are opened but not yet ended, this is
+    // negative when there are more 'end's than blocks
+    fn unclosed_blocks(tokens: &[Token]) -> isize {
+        tokens
+            .iter()
+            .map(|t| match t.token_type {
+                TokenType::If
+                | TokenType::Cond
+                | TokenType::While
+                | TokenType::For
+                | TokenType::Repeat
+                | TokenType::Proc
+                | TokenType::LBracket => 1,
+                TokenType::End | TokenType::RBracket => -1,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    // the REPL history is kept in $CONSTANT_HISTORY, or ~/.constant_history when that
+    // isn't set, setting it to an empty string turns the history off
+    fn history_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("CONSTANT_HISTORY") {
+            return (!path.is_empty()).then(|| path.into());
+        }
+
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".constant_history"))
+    }
+
+    // runs a REPL meta-command, returning false when the input isn't one
+    fn repl_command(&mut self, command: &str) -> Result<bool, ConstantError> {
+        match command {
+            ".stack" => writeln!(self.output, "{}", self.format_stack())?,
+            ".clear" => self.stack.clear(),
+            ".procs" => {
+                let mut names = self.procs.keys().cloned().collect::<Vec<String>>();
+                names.sort();
+                writeln!(self.output, "{}", names.join(" "))?;
+            }
+            // runs a file against the session's stack, bindings, and procedures,
+            // it replaces the program in the same way each line typed in does
+            _ if command.starts_with(".load ") => {
+                let path = command[".load ".len()..].trim();
+                let source = std::fs::read_to_string(path)
+                    .map_err(|_| ConstantError::SourceFileNotFound(path.into()))?;
+                let tokens = Lexer::new(&source).tokenize()?;
+                self.program = Parser::new(&tokens).parse()?;
+                self.interpret()?;
+            }
+            _ => return Ok(false),
+        }
+
+        self.output.flush()?;
+        Ok(true)
+    }
+
+    pub fn repl(&mut self) {
+        println!("Welcome to the Constant REPL, type 'exit' or 'quit' to quit");
+        println!("'.stack' shows the stack, '.clear' empties it, and '.procs' lists procedures");
+        println!("'.load <path>' runs a file in the current session");
+
+        let mut editor = DefaultEditor::new().expect("Error: Could not start the line editor");
+        let history = Self::history_path();
+        if let Some(path) = &history {
+            // there won't be a history file the first time the REPL is used
+            let _ = editor.load_history(path);
+        }
+
+        'repl: loop {
+            // keeps reading lines until every block that was opened has been closed
+            let mut code = String::new();
+            let mut prompt = "> ";
+            loop {
+                match editor.readline(prompt) {
+                    Ok(line) => {
+                        code.push_str(&line);
+                        code.push('\n');
+                    }
+                    // ctrl-c throws away what has been typed so far
+                    Err(ReadlineError::Interrupted) => continue 'repl,
+                    Err(_) => break 'repl,
+                }
+
+                match Lexer::new(&code).tokenize() {
+                    Ok(tokens) if Self::unclosed_blocks(&tokens) > 0 => prompt = "... ",
+                    _ => break,
+                }
+            }
+
+            if !code.trim().is_empty() {
+                let _ = editor.add_history_entry(code.trim_end());
+            }
+
+            if code.trim() == "exit" || code.trim() == "quit" {
+                break;
+            }
+
+            match self.repl_command(code.trim()) {
+                Ok(true) => continue,
+                Ok(false) => (),
+                Err(e) => {
+                    println!("{e}");
+                    continue;
+                }
+            }
+
+            let tokens = match Lexer::new(&code).tokenize() {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    println!("{e}");
+                    continue;
+                }
+            };
+            let ast = match Parser::new(&tokens).parse() {
+                Ok(a) => a,
+                Err(e) => {
+                    println!("{e}");
+                    continue;
+                }
+            };
+
+            self.program = ast;
+
+            match self.interpret() {
+                Ok(()) => (),
+                Err(ConstantError::Exit(_)) => break,
+                Err(e) => println!("{e}"),
+            }
+        }
+
+        if let Some(path) = &history {
+            if let Err(e) = editor.save_history(path) {
+                println!("Could not save the REPL history: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, io::Cursor, rc::Rc};
+
+    use super::*;
+
+    // a writer that can still be read after being moved into the interpreter
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run_output(source: &str) -> Result<String, ConstantError> {
+        let tokens = Lexer::new(source).tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+        let output = Output::default();
+        Interpreter::with_writer(ast, output.clone()).interpret()?;
+
+        let bytes = output.0.borrow().clone();
+        Ok(String::from_utf8(bytes).unwrap())
+    }
+
+    fn load(source: &str) -> Result<Interpreter, ConstantError> {
+        let tokens = Lexer::new(source).tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+
+        Ok(Interpreter::new(ast))
+    }
+
+    fn run(source: &str) -> Result<Interpreter, ConstantError> {
+        let mut interpreter = load(source)?;
+        interpreter.interpret()?;
+
+        Ok(interpreter)
+    }
+
+    #[test]
+    fn interpret_print() -> Result<(), ConstantError> {
+        assert_eq!(run_output("\"hello\" print 1 2 + print")?, "hello\n3\n");
+        assert_eq!(run_output("1 2 +")?, "");
+        assert_eq!(
+            run_output("\"http://example.com\" print")?,
+            "http://example.com\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_large_program() -> Result<(), ConstantError> {
+        let source = "1 +\n".repeat(10_000);
+        let mut interpreter = load(&format!("0\n{source}"))?;
+        interpreter.interpret()?;
+        assert_eq!(interpreter.stack, vec![Literal::Int(10_000)]);
+
+        // the program is still there to be run again
+        assert_eq!(interpreter.program.len(), 20_002);
+        interpreter.interpret()?;
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(10_000), Literal::Int(10_000)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_stack() -> Result<(), ConstantError> {
+        let interpreter = run("2 3 + \"a\"")?;
+        assert_eq!(
+            interpreter.stack(),
+            &[Literal::Int(5), Literal::String("a".into())]
+        );
+        assert_eq!(
+            interpreter.into_stack(),
+            vec![Literal::Int(5), Literal::String("a".into())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_write() -> Result<(), ConstantError> {
+        assert_eq!(run_output("\"a\" write \"b\" write")?, "ab");
+        assert_eq!(run_output("1 write 2 print 3 write")?, "12\n3");
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_trace() -> Result<(), ConstantError> {
+        let source = "1 2 + print 0 3 for do end";
+
+        let tokens = Lexer::new(source).tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+        let output = Output::default();
+        let trace = Output::default();
+        let mut interpreter = Interpreter::with_writer(ast, output.clone());
+        interpreter.set_trace_writer(trace.clone());
+        interpreter.interpret()?;
+
+        assert_eq!(
+            String::from_utf8_lossy(&trace.0.borrow()),
+            "Push(Literal(Int(1))) -> [1]\n\
+             Push(Literal(Int(2))) -> [1, 2]\n\
+             DoubleOperation(Add) -> [3]\n\
+             SingleOperation(Print) -> []\n\
+             Push(Literal(Int(0))) -> [0]\n\
+             Push(Literal(Int(3))) -> [0, 3]\n\
+             For([]) -> [0, 1, 2]\n\
+             Empty -> [0, 1, 2]\n"
+        );
+        assert_eq!(output.0.borrow().as_slice(), run_output(source)?.as_bytes());
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(0), Literal::Int(1), Literal::Int(2)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_logical() -> Result<(), ConstantError> {
+        assert_eq!(run("true false and")?.stack, vec![Literal::Bool(false)]);
+        assert_eq!(run("true false or")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run("true true &&")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run("false false ||")?.stack, vec![Literal::Bool(false)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_bitwise() -> Result<(), ConstantError> {
+        assert_eq!(run("6 3 band")?.stack, vec![Literal::Int(2)]);
+        assert_eq!(run("6 3 bor")?.stack, vec![Literal::Int(7)]);
+        assert_eq!(run("6 3 bxor")?.stack, vec![Literal::Int(5)]);
+        assert_eq!(run("1 4 shl")?.stack, vec![Literal::Int(16)]);
+        assert_eq!(run("16 2 shr")?.stack, vec![Literal::Int(4)]);
+
+        let mut interpreter = load("1.5 1 band")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Number(1.5), Literal::Int(1)]
+        );
+        assert!(run("1 64 shl").is_err());
+        assert!(run("1 -1 shr").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_math() -> Result<(), ConstantError> {
+        assert_eq!(run("9 sqrt")?.stack, vec![Literal::Number(3.0)]);
+        assert_eq!(run("-5 abs")?.stack, vec![Literal::Int(5)]);
+        assert_eq!(run("-2.5 abs")?.stack, vec![Literal::Number(2.5)]);
+        assert_eq!(run("2.7 floor")?.stack, vec![Literal::Number(2.0)]);
+        assert_eq!(run("2.2 ceil")?.stack, vec![Literal::Number(3.0)]);
+        assert_eq!(run("2.5 round")?.stack, vec![Literal::Number(3.0)]);
+        assert_eq!(run("4 floor")?.stack, vec![Literal::Int(4)]);
+
+        let mut interpreter = load("-4 sqrt")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(-4)]);
+        assert!(run("\"a\" abs").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_string_repeat() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("\"ab\" 3 *")?.stack,
+            vec![Literal::String("ababab".into())]
+        );
+        assert_eq!(run("\"ab\" -1 *")?.stack, vec![Literal::String("".into())]);
+        assert_eq!(
+            run("\"ab\" 2.5 *")?.stack,
+            vec![Literal::String("abab".into())]
+        );
+
+        for source in ["\"ab\" 9223372036854775807 *", "\"a\" 1e30 *"] {
+            let mut interpreter = load(source)?;
+            assert!(
+                matches!(
+                    interpreter.interpret(),
+                    Err(ConstantError::InvalidOperation(_))
+                ),
+                "{source}"
+            );
+            assert_eq!(interpreter.stack.len(), 2);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_min_max() -> Result<(), ConstantError> {
+        assert_eq!(run("3 7 max")?.stack, vec![Literal::Int(7)]);
+        assert_eq!(run("3 7 min")?.stack, vec![Literal::Int(3)]);
+        assert_eq!(run("2.5 2 max")?.stack, vec![Literal::Number(2.5)]);
+        assert_eq!(run("4 4 min")?.stack, vec![Literal::Int(4)]);
+
+        let mut interpreter = load("1 \"a\" max")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(1), Literal::String("a".into())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_neg() -> Result<(), ConstantError> {
+        assert_eq!(run("5 neg")?.stack, vec![Literal::Int(-5)]);
+        assert_eq!(run("-3 neg")?.stack, vec![Literal::Int(3)]);
+        assert_eq!(run("1.5 neg")?.stack, vec![Literal::Number(-1.5)]);
+
+        let mut interpreter = load("true neg")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Bool(true)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_pow() -> Result<(), ConstantError> {
+        assert_eq!(run("2 10 pow")?.stack, vec![Literal::Int(1024)]);
+        assert_eq!(run("0 0 pow")?.stack, vec![Literal::Int(1)]);
+        assert_eq!(run("2 -1 pow")?.stack, vec![Literal::Number(0.5)]);
+        assert_eq!(run("9 0.5 pow")?.stack, vec![Literal::Number(3.0)]);
+
+        let mut interpreter = load("-8 0.5 pow")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(-8), Literal::Number(0.5)]
+        );
+        assert!(run("2 64 pow").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_mixed_comparison() -> Result<(), ConstantError> {
+        let mut interpreter = load("5 \"a\" >")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(msg)) if msg == "Cannot compare number with string"
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(5), Literal::String("a".into())]
+        );
+        assert!(run("true 1 ==").is_err());
+
+        assert_eq!(
+            run("\"apple\" \"banana\" <")?.stack,
+            vec![Literal::Bool(true)]
+        );
+        assert_eq!(run("\"b\" \"a\" >")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run("1 1.0 ==")?.stack, vec![Literal::Bool(true)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_ordering() -> Result<(), ConstantError> {
+        assert_eq!(run("true true ==")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run("true false !=")?.stack, vec![Literal::Bool(true)]);
+
+        let mut interpreter = load("true false >")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Bool(true), Literal::Bool(false)]
+        );
+        assert!(run("false true <=").is_err());
+
+        assert_eq!(run("\"abc\" \"abd\" <")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run("\"abc\" \"ab\" >=")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run("\"Z\" \"a\" <")?.stack, vec![Literal::Bool(true)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_char_at() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("\"hello\" 1 char_at")?.stack,
+            vec![Literal::String("e".into())]
+        );
+        assert_eq!(
+            run("\"héllo\" 2 char_at")?.stack,
+            vec![Literal::String("l".into())]
+        );
+
+        let mut interpreter = load("\"hello\" 5 char_at")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::String("hello".into()), Literal::Int(5)]
+        );
+        assert!(run("\"hello\" -1 char_at").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_substring() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("\"hello world\" 6 5 substring")?.stack,
+            vec![Literal::String("world".into())]
+        );
+        assert_eq!(
+            run("\"héllo\" 1 3 substring")?.stack,
+            vec![Literal::String("éll".into())]
+        );
+
+        let mut interpreter = load("\"hello\" 3 5 substring")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![
+                Literal::String("hello".into()),
+                Literal::Int(3),
+                Literal::Int(5)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_contains() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("\"hello world\" \"o w\" contains")?.stack,
+            vec![Literal::Bool(true)]
+        );
+        assert_eq!(
+            run("\"hello world\" \"planet\" contains")?.stack,
+            vec![Literal::Bool(false)]
+        );
+        assert_eq!(
+            run("\"hello\" \"\" contains")?.stack,
+            vec![Literal::Bool(true)]
+        );
+
+        let mut interpreter = load("\"hello\" 1 contains")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::String("hello".into()), Literal::Int(1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_starts_ends_with() -> Result<(), ConstantError> {
+        let check = |source: &str, expected: bool| -> Result<(), ConstantError> {
+            assert_eq!(run(source)?.stack, vec![Literal::Bool(expected)]);
+            Ok(())
+        };
+
+        check("\"hello\" \"he\" starts_with", true)?;
+        check("\"hello\" \"lo\" starts_with", false)?;
+        check("\"hello\" \"lo\" ends_with", true)?;
+        check("\"hello\" \"he\" ends_with", false)?;
+        check("\"hello\" \"\" starts_with", true)?;
+        check("\"hello\" \"\" ends_with", true)?;
+
+        let mut interpreter = load("1 \"a\" ends_with")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(1), Literal::String("a".into())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_replace() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("\"hello\" \"l\" \"L\" replace")?.stack,
+            vec![Literal::String("heLLo".into())]
+        );
+        assert_eq!(
+            run("\"hello\" \"x\" \"y\" replace")?.stack,
+            vec![Literal::String("hello".into())]
+        );
+        assert_eq!(
+            run("\"hello\" \"\" \"y\" replace")?.stack,
+            vec![Literal::String("hello".into())]
+        );
+        assert_eq!(
+            run("\"a, b\" \", \" \"\" replace")?.stack,
+            vec![Literal::String("ab".into())]
+        );
+
+        let mut interpreter = load("\"hello\" \"l\" 1 replace")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![
+                Literal::String("hello".into()),
+                Literal::String("l".into()),
+                Literal::Int(1)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_trim() -> Result<(), ConstantError> {
+        let trimmed = |source: &str| -> Result<Vec<Literal>, ConstantError> {
+            Ok(run(&format!("\" \\t a b \\n\" {source}"))?.stack)
+        };
+
+        assert_eq!(trimmed("trim")?, vec![Literal::String("a b".into())]);
+        assert_eq!(
+            trimmed("trim_start")?,
+            vec![Literal::String("a b \n".into())]
+        );
+        assert_eq!(
+            trimmed("trim_end")?,
+            vec![Literal::String(" \t a b".into())]
+        );
+        assert_eq!(
+            run("\"  \\n \" trim")?.stack,
+            vec![Literal::String("".into())]
+        );
+        assert_eq!(
+            run("\"abc\" trim")?.stack,
+            vec![Literal::String("abc".into())]
+        );
+
+        let mut interpreter = load("1 trim")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_reverse() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("\"abc\" reverse")?.stack,
+            vec![Literal::String("cba".into())]
+        );
+        assert_eq!(run("\"\" reverse")?.stack, vec![Literal::String("".into())]);
+        assert_eq!(
+            run("\"héllo\" reverse")?.stack,
+            vec![Literal::String("olléh".into())]
+        );
+
+        let mut interpreter = load("1 reverse")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_case() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("\"Hello\" to_upper")?.stack,
+            vec![Literal::String("HELLO".into())]
+        );
+        assert_eq!(
+            run("\"Hello\" to_lower")?.stack,
+            vec![Literal::String("hello".into())]
+        );
+        assert_eq!(
+            run("\"straße\" to_upper")?.stack,
+            vec![Literal::String("STRASSE".into())]
+        );
+
+        let mut interpreter = load("5 to_upper")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(5)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_split() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("\"a,b,c\" \",\" split")?.stack,
+            vec![
+                Literal::String("a".into()),
+                Literal::String("b".into()),
+                Literal::String("c".into()),
+                Literal::Int(3)
+            ]
+        );
+        assert_eq!(
+            run("\"abc\" \",\" split")?.stack,
+            vec![Literal::String("abc".into()), Literal::Int(1)]
+        );
+        assert_eq!(
+            run("\"ab\" \"\" split")?.stack,
+            vec![
+                Literal::String("a".into()),
+                Literal::String("b".into()),
+                Literal::Int(2)
+            ]
+        );
+
+        let mut interpreter = load("\"a b\" 1 split")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::String("a b".into()), Literal::Int(1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_format() -> Result<(), ConstantError> {
+        assert_eq!(run_output("3 \"x={}\" format print")?, "x=3\n");
+        assert_eq!(
+            run_output("1 \"a\" true \"{}, {} and {}!\" format print")?,
+            "1, a and true!\n"
+        );
+        // values below the ones used are left alone
+        assert_eq!(
+            run("1 2 \"{}\" format")?.stack,
+            vec![Literal::Int(1), Literal::String("2".into())]
+        );
+        assert_eq!(
+            run("\"no placeholders\" format")?.stack,
+            vec![Literal::String("no placeholders".into())]
+        );
+
+        let mut interpreter = load("1 \"{} {}\" format")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidStackAmount(_, 3))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(1), Literal::String("{} {}".into())]
+        );
+        assert!(matches!(
+            run("1 2 format"),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_list() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("list_new 1 list_push \"a\" list_push")?.stack,
+            vec![Literal::List(vec![
+                Literal::Int(1),
+                Literal::String("a".into())
+            ])]
+        );
+        assert_eq!(
+            run("list_new 1 list_push 2 list_push list_len")?.stack,
+            vec![Literal::Int(2)]
+        );
+        assert_eq!(
+            run_output("list_new 1 list_push 2 list_push 3 list_push print")?,
+            "[1, 2, 3]\n"
+        );
+
+        let mut interpreter = load("1 2 list_push")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1), Literal::Int(2)]);
+        assert!(run("list_new list_new ==").is_err());
+        assert!(run("list_new 1 +").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_repl_command() -> Result<(), ConstantError> {
+        let tokens = Lexer::new("1 \"a\" proc b do end proc a do end").tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+        let output = Output::default();
+        let mut interpreter = Interpreter::with_writer(ast, output.clone());
+        interpreter.interpret()?;
+
+        assert!(interpreter.repl_command(".stack")?);
+        assert!(interpreter.repl_command(".procs")?);
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(1), Literal::String("a".into())]
+        );
+        assert!(interpreter.repl_command(".clear")?);
+        assert!(interpreter.stack.is_empty());
+        assert!(!interpreter.repl_command("1 2 +")?);
+
+        let bytes = output.0.borrow().clone();
+        assert_eq!(String::from_utf8(bytes).unwrap(), "[1, a]\na b\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_repl_load() -> Result<(), ConstantError> {
+        let path = std::env::temp_dir().join("constant_repl_load.cst");
+        std::fs::write(&path, "2 * bind doubled proc inc do 1 + end")?;
+
+        let mut interpreter = load("21")?;
+        interpreter.interpret()?;
+        assert!(interpreter.repl_command(&format!(".load {}", path.display()))?);
+        std::fs::remove_file(&path)?;
+
+        interpreter.program = Parser::new(&Lexer::new("doubled call inc").tokenize()?).parse()?;
+        interpreter.interpret()?;
+        assert_eq!(interpreter.stack, vec![Literal::Int(43)]);
+
+        assert!(matches!(
+            interpreter.repl_command(".load missing.cst"),
+            Err(ConstantError::SourceFileNotFound(p)) if p == "missing.cst"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_unclosed_blocks() -> Result<(), ConstantError> {
+        let unclosed = |source| -> Result<isize, ConstantError> {
+            Ok(Interpreter::unclosed_blocks(
+                &Lexer::new(source).tokenize()?,
+            ))
+        };
+
+        assert_eq!(unclosed("1 2 +")?, 0);
+        assert_eq!(unclosed("proc a do\n")?, 1);
+        assert_eq!(unclosed("proc a do\nif true do\nend\n")?, 1);
+        assert_eq!(unclosed("while true do\nbreak\nend\n")?, 0);
+        assert_eq!(unclosed("end")?, -1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_arithmetic_words() -> Result<(), ConstantError> {
+        for (word, symbol) in [
+            ("add", "+"),
+            ("sub", "-"),
+            ("mul", "*"),
+            ("div", "/"),
+            ("mod", "%"),
+        ] {
+            assert_eq!(
+                run(&format!("7 3 {word}"))?.stack,
+                run(&format!("7 3 {symbol}"))?.stack
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_char() -> Result<(), ConstantError> {
+        assert_eq!(run("'a' 1 +")?.stack, vec![Literal::Char('b')]);
+        assert_eq!(run("'c' 2 -")?.stack, vec![Literal::Char('a')]);
+        assert_eq!(run("'a' 'b' <")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run_output("'x' print '\\n' write")?, "x\n\n");
+
+        let mut interpreter = load("'a' 'b' +")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Char('a'), Literal::Char('b')]
+        );
+        assert!(run("'a' -98 +").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_ord_chr() -> Result<(), ConstantError> {
+        assert_eq!(run("\"A\" ord")?.stack, vec![Literal::Int(65)]);
+        assert_eq!(run("'é' ord")?.stack, vec![Literal::Int(233)]);
+        assert_eq!(run("65 chr")?.stack, vec![Literal::Char('A')]);
+
+        for source in ["\"AB\" ord", "55296 chr", "-1 chr"] {
+            let mut interpreter = load(source)?;
+            assert!(matches!(
+                interpreter.interpret(),
+                Err(ConstantError::InvalidOperation(_))
+            ));
+            assert_eq!(interpreter.stack.len(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_negative_number() -> Result<(), ConstantError> {
+        assert_eq!(run("-5 3 +")?.stack, vec![Literal::Int(-2)]);
+        assert_eq!(run("3 -5 -")?.stack, vec![Literal::Int(8)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_rot() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("1 2 3 rot")?.stack,
+            vec![Literal::Int(2), Literal::Int(3), Literal::Int(1)]
+        );
+
+        let mut interpreter = load("1 2 rot")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidStackAmount(_, 3))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1), Literal::Int(2)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_reverse_rot() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("1 2 3 -rot")?.stack,
+            vec![Literal::Int(3), Literal::Int(1), Literal::Int(2)]
+        );
+        assert_eq!(
+            run("1 2 3 rotd")?.stack,
+            vec![Literal::Int(3), Literal::Int(1), Literal::Int(2)]
+        );
+        assert_eq!(
+            run("1 2 3 rot -rot")?.stack,
+            vec![Literal::Int(1), Literal::Int(2), Literal::Int(3)]
+        );
+
+        let mut interpreter = load("1 2 -rot")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidStackAmount(ref a, 3)) if a == "Reverse rotating"
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1), Literal::Int(2)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_roll() -> Result<(), ConstantError> {
+        assert_eq!(run("1 2 3 2 roll")?.stack, run("1 2 3 swap")?.stack);
+        assert_eq!(run("1 2 3 3 roll")?.stack, run("1 2 3 rot")?.stack);
+        assert_eq!(
+            run("1 2 3 4 4 roll")?.stack,
+            vec![
+                Literal::Int(2),
+                Literal::Int(3),
+                Literal::Int(4),
+                Literal::Int(1)
+            ]
+        );
+        assert_eq!(
+            run("1 2 0 roll 1 roll")?.stack,
+            vec![Literal::Int(1), Literal::Int(2)]
+        );
+
+        let mut interpreter = load("1 2 3 roll")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidStackAmount(_, 4))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(1), Literal::Int(2), Literal::Int(3)]
+        );
+        assert!(matches!(
+            run("1 2 -1 roll"),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert!(matches!(
+            run("1 2 2.0 roll"),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_roll_stack() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("1 2 3 roll_up")?.stack,
+            vec![Literal::Int(2), Literal::Int(3), Literal::Int(1)]
+        );
+        assert_eq!(
+            run("1 2 3 roll_down")?.stack,
+            vec![Literal::Int(3), Literal::Int(1), Literal::Int(2)]
+        );
+        assert_eq!(
+            run("1 2 3 roll_up roll_down")?.stack,
+            vec![Literal::Int(1), Literal::Int(2), Literal::Int(3)]
+        );
+        assert!(run("roll_up roll_down")?.stack.is_empty());
+        assert_eq!(run("1 roll_up roll_down")?.stack, vec![Literal::Int(1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_swap() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("1 2 swap")?.stack,
+            vec![Literal::Int(2), Literal::Int(1)]
+        );
+        assert_eq!(
+            run("0 1 2 swap")?.stack,
+            vec![Literal::Int(0), Literal::Int(2), Literal::Int(1)]
+        );
+
+        let mut interpreter = load("1 swap")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidStackAmount(_, 2))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_pairs() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("1 2 2dup")?.stack,
+            vec![
+                Literal::Int(1),
+                Literal::Int(2),
+                Literal::Int(1),
+                Literal::Int(2)
+            ]
+        );
+        assert_eq!(run("1 2 3 2drop")?.stack, vec![Literal::Int(1)]);
+
+        for source in ["1 2dup", "1 2drop"] {
+            let mut interpreter = load(source)?;
+            assert!(matches!(
+                interpreter.interpret(),
+                Err(ConstantError::InvalidStackAmount(_, 2))
+            ));
+            assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_nip_tuck() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("1 2 3 nip")?.stack,
+            vec![Literal::Int(1), Literal::Int(3)]
+        );
+        assert_eq!(
+            run("1 2 tuck")?.stack,
+            vec![Literal::Int(2), Literal::Int(1), Literal::Int(2)]
+        );
+
+        for source in ["1 nip", "1 tuck"] {
+            let mut interpreter = load(source)?;
+            assert!(matches!(
+                interpreter.interpret(),
+                Err(ConstantError::InvalidStackAmount(_, 2))
+            ));
+            assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_condition() -> Result<(), ConstantError> {
+        let mut interpreter = load("1 if 2 do end")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(msg)) if msg.ends_with("found a number")
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1), Literal::Int(2)]);
+
+        let mut interpreter = load("while do end")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(msg)) if msg.ends_with("the stack is empty")
+        ));
+        assert!(interpreter.stack.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_read() -> Result<(), ConstantError> {
+        let tokens = Lexer::new("read read").tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+        let input = Cursor::new("first line\r\nsecond\n");
+        let mut interpreter = Interpreter::with_io(ast, input, Output::default());
+        interpreter.interpret()?;
+        assert_eq!(
+            interpreter.stack,
+            vec![
+                Literal::String("first line".into()),
+                Literal::String("second".into())
+            ]
+        );
+
+        let tokens = Lexer::new("read").tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+        let mut interpreter = Interpreter::with_io(ast, Cursor::new(""), Output::default());
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::EndOfInput)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_input() -> Result<(), ConstantError> {
+        let tokens = Lexer::new("\"name: \" input").tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+        let output = Output::default();
+        let mut interpreter = Interpreter::with_io(ast, Cursor::new("  bob \n"), output.clone());
+        interpreter.interpret()?;
+        assert_eq!(interpreter.stack, vec![Literal::String("bob".into())]);
+        assert_eq!(output.0.borrow().as_slice(), b"name: ");
+
+        // the prompt is put back when there's nothing left to read
+        let mut interpreter =
+            Interpreter::with_io(interpreter.program, Cursor::new(""), Output::default());
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::EndOfInput)
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::String("name: ".into())]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_input_prompt() -> Result<(), ConstantError> {
+        let mut interpreter = load("1 input")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+        assert!(matches!(
+            run("input"),
+            Err(ConstantError::InvalidStackAmount(_, 1))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_assert() -> Result<(), ConstantError> {
+        assert!(run("true assert")?.stack.is_empty());
+        assert!(run("1 1 + 2 == assert")?.stack.is_empty());
+        assert!(matches!(
+            run_output("false assert \"unreachable\" print"),
+            Err(ConstantError::AssertionFailed)
+        ));
+
+        let mut interpreter = load("1 assert")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_exit() -> Result<(), ConstantError> {
+        let output = Output::default();
+        let tokens = Lexer::new("\"before\" print 3 exit \"after\" print").tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+        let res = Interpreter::with_writer(ast, output.clone()).interpret();
+
+        assert!(matches!(res, Err(ConstantError::Exit(3))));
+        let bytes = output.0.borrow().clone();
+        assert_eq!(String::from_utf8(bytes).unwrap(), "before\n");
+        assert!(matches!(run("0 exit"), Err(ConstantError::Exit(0))));
+
+        let mut interpreter = load("1.5 exit")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Number(1.5)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_dump() -> Result<(), ConstantError> {
+        assert_eq!(run_output("1 2 3 dump")?, "[1, 2, 3]\n");
+        assert_eq!(run_output("dump")?, "[]\n");
+        // dumping leaves the stack as it was
+        let tokens = Lexer::new("1 \"a\" dump").tokenize()?;
+        let ast = Parser::new(&tokens).parse()?;
+        let output = Output::default();
+        let mut interpreter = Interpreter::with_writer(ast, output.clone());
+        interpreter.interpret()?;
+        assert_eq!(String::from_utf8_lossy(&output.0.borrow()), "[1, a]\n");
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(1), Literal::String("a".into())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_defined() -> Result<(), ConstantError> {
+        assert_eq!(run("1 bind x defined x")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run("defined x")?.stack, vec![Literal::Bool(false)]);
+        assert_eq!(
+            run("proc p do end defined p")?.stack,
+            vec![Literal::Bool(true)]
+        );
+        assert_eq!(
+            run("proc p do 1 bind y end call p defined y")?.stack,
+            vec![Literal::Bool(false)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_strict() -> Result<(), ConstantError> {
+        let source = "proc p do 1 end proc p do 2 end call p";
+        assert_eq!(run(source)?.stack, vec![Literal::Int(2)]);
+
+        let mut interpreter = load(source)?;
+        interpreter.set_strict(true);
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::ProcAlreadyExists(name)) if name == "p"
+        ));
+
+        // an identical body is still a second definition
+        let mut interpreter = load("proc p do 1 end proc p do 1 end call p")?;
+        interpreter.set_strict(true);
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::ProcAlreadyExists(name)) if name == "p"
+        ));
+
+        let mut interpreter = load("2 repeat do proc p do 1 end end")?;
+        interpreter.set_strict(true);
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::ProcAlreadyExists(name)) if name == "p"
+        ));
+
+        let mut interpreter = load("call p proc p do 1 end call p")?;
+        interpreter.set_strict(true);
+        interpreter.interpret()?;
+        assert_eq!(interpreter.stack, vec![Literal::Int(1), Literal::Int(1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_unbind() -> Result<(), ConstantError> {
+        assert!(matches!(
+            run("1 bind x unbind x x"),
+            Err(ConstantError::IdentDoesNotExist(name)) if name == "x"
+        ));
+        assert_eq!(
+            run("1 bind x unbind x defined x")?.stack,
+            vec![Literal::Bool(false)]
+        );
+        assert!(matches!(
+            run("unbind x"),
+            Err(ConstantError::IdentDoesNotExist(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_const() -> Result<(), ConstantError> {
+        assert_eq!(run("1 const x x x +")?.stack, vec![Literal::Int(2)]);
+        assert_eq!(run("1 bind x 2 bind x x")?.stack, vec![Literal::Int(2)]);
+
+        let mut interpreter = load("1 const x 2 bind x")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::ConstantReassignment(name)) if name == "x"
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(2)]);
+        assert!(matches!(
+            run("1 const x 2 const x"),
+            Err(ConstantError::ConstantReassignment(_))
+        ));
+        assert!(matches!(
+            run("1 const x unbind x"),
+            Err(ConstantError::ConstantReassignment(_))
+        ));
+        assert!(matches!(
+            run("1 const x proc x do end"),
+            Err(ConstantError::ConstantReassignment(_))
+        ));
+
+        // a procedure's scope can shadow a constant and make its own
+        assert_eq!(
+            run("1 const x proc p do 2 bind x x 3 const y y end call p x")?.stack,
+            vec![Literal::Int(2), Literal::Int(3), Literal::Int(1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_cond() -> Result<(), ConstantError> {
+        let cond = |x: i64| {
+            format!(
+                "{x} bind x cond case x 1 == do \"a\" case x 2 >= do \"b\" case x 3 == do \"c\" else do \"d\" end"
+            )
+        };
+
+        assert_eq!(run(&cond(1))?.stack, vec![Literal::String("a".into())]);
+        // a later case runs when the earlier ones are false, but only the first true one
+        assert_eq!(run(&cond(3))?.stack, vec![Literal::String("b".into())]);
+        assert_eq!(run(&cond(0))?.stack, vec![Literal::String("d".into())]);
+        assert!(run("cond case false do 1 end")?.stack.is_empty());
+
+        let mut interpreter = load("cond case false do 1 case 5 do 2 end")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(msg)) if msg.starts_with("Cond statement")
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(5)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_elif() -> Result<(), ConstantError> {
+        let chain = |x: i64| {
+            format!("{x} bind x if x 1 == do \"a\" elif x 2 == do \"b\" elif x 2 >= do \"c\" end")
+        };
+
+        assert_eq!(run(&chain(1))?.stack, vec![Literal::String("a".into())]);
+        // only the first true elif runs even when a later one is also true
+        assert_eq!(run(&chain(2))?.stack, vec![Literal::String("b".into())]);
+        assert_eq!(run(&chain(3))?.stack, vec![Literal::String("c".into())]);
+        assert!(run(&chain(0))?.stack.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_repeat() -> Result<(), ConstantError> {
+        assert_eq!(run_output("3 repeat do \"hi\" print end")?, "hi\nhi\nhi\n");
+        assert_eq!(run_output("repeat 1 1 + do \"hi\" print end")?, "hi\nhi\n");
+        assert_eq!(run_output("0 repeat do \"hi\" print end")?, "");
+        assert_eq!(
+            run("0 5 repeat do 1 + dup 3 == if do break end end")?.stack,
+            vec![Literal::Int(3)]
+        );
+
+        for source in ["-1 repeat do end", "1.5 repeat do end"] {
+            let mut interpreter = load(source)?;
+            assert!(matches!(
+                interpreter.interpret(),
+                Err(ConstantError::InvalidOperation(_))
+            ));
+            assert_eq!(interpreter.stack.len(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_block() -> Result<(), ConstantError> {
+        assert_eq!(run("[ 1 2 + ] apply")?.stack, vec![Literal::Int(3)]);
+        assert_eq!(
+            run("2 [ dup * ] bind square square apply square apply")?.stack,
+            vec![Literal::Int(16)]
+        );
+        assert_eq!(run_output("[ 1 ] print")?, "<block>\n");
+        assert_eq!(
+            run("0 while true do 1 + dup 3 == [ break ] [ ] select apply end")?.stack,
+            vec![Literal::Int(3)]
+        );
+
+        let mut interpreter = load("1 apply")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+        assert!(run("[ 1 ] [ 1 ] ==").is_err());
+
+        assert!(matches!(
+            run("[ dup apply ] dup apply"),
+            Err(ConstantError::RecursionLimitExceeded(
+                DEFAULT_RECURSION_LIMIT
+            ))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_times() -> Result<(), ConstantError> {
+        assert_eq!(run_output("3 [ \"hi\" print ] times")?, "hi\nhi\nhi\n");
+        assert_eq!(run_output("0 [ \"hi\" print ] times")?, "");
+        assert_eq!(
+            run("1 10 [ 2 * dup 8 == if do break end ] times")?.stack,
+            vec![Literal::Int(8)]
+        );
+
+        for source in ["[ ] [ ] times", "-1 [ ] times", "2 3 times"] {
+            let mut interpreter = load(source)?;
+            assert!(matches!(
+                interpreter.interpret(),
+                Err(ConstantError::InvalidOperation(_))
+            ));
+            assert_eq!(interpreter.stack.len(), 2);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_over() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("1 2 over")?.stack,
+            vec![Literal::Int(1), Literal::Int(2), Literal::Int(1)]
+        );
+
+        let mut interpreter = load("1 over")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidStackAmount(_, 2))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_int() -> Result<(), ConstantError> {
+        assert_eq!(run("2 3 +")?.stack, vec![Literal::Int(5)]);
+        assert_eq!(run("2 0.5 *")?.stack, vec![Literal::Number(1.0)]);
+        assert_eq!(run("10 4 /")?.stack, vec![Literal::Number(2.5)]);
+        assert_eq!(run("10 4 %")?.stack, vec![Literal::Int(2)]);
+        assert_eq!(run("1 1.0 ==")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run("1 1.5 <")?.stack, vec![Literal::Bool(true)]);
+        assert_eq!(run_output("7 print 7.5 print")?, "7\n7.5\n");
+
+        assert!(matches!(
+            run("9223372036854775807 1 +"),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_mismatch_message() -> Result<(), ConstantError> {
+        for (source, expected) in [
+            ("true 1 +", "Cannot add bool and number"),
+            ("1 true +", "Cannot add number and bool"),
+            ("\"a\" 1 -", "Cannot subtract string and number"),
+            ("1 \"a\" *", "Cannot multiply number and string"),
+            ("'a' 2.5 /", "Cannot divide char and number"),
+            ("list_new 2 %", "Cannot mod list and number"),
+        ] {
+            match run(source) {
+                Err(ConstantError::InvalidOperation(msg)) => assert_eq!(msg, expected),
+                res => panic!("{source:?} gave {:?}", res.map(|i| i.stack)),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_idiv() -> Result<(), ConstantError> {
+        assert_eq!(run("7 2 idiv")?.stack, vec![Literal::Int(3)]);
+        assert_eq!(run("-7 2 idiv")?.stack, vec![Literal::Int(-3)]);
+        assert_eq!(run("7.5 2 idiv")?.stack, vec![Literal::Number(3.0)]);
+
+        let mut interpreter = load("7 0 idiv")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(e)) if e == "Division by zero"
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(7), Literal::Int(0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_by_zero() -> Result<(), ConstantError> {
+        for source in ["5 0 /", "5 0.0 /", "5.5 0 /"] {
+            let mut interpreter = load(source)?;
+            assert!(matches!(
+                interpreter.interpret(),
+                Err(ConstantError::InvalidOperation(e)) if e == "Division by zero"
+            ));
+            assert_eq!(interpreter.stack.len(), 2);
+        }
+
+        for source in ["5 0 %", "5 0.0 %", "5.5 0 %"] {
+            let mut interpreter = load(source)?;
+            assert!(matches!(
+                interpreter.interpret(),
+                Err(ConstantError::InvalidOperation(e)) if e == "Modulo by zero"
+            ));
+            assert_eq!(interpreter.stack.len(), 2);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_clear() -> Result<(), ConstantError> {
+        assert!(run("1 \"two\" true clear")?.stack().is_empty());
+        assert!(run("clear")?.stack().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_depth() -> Result<(), ConstantError> {
+        assert_eq!(run("depth")?.stack, vec![Literal::Int(0)]);
+        assert_eq!(run("1 2 3 depth")?.stack.last(), Some(&Literal::Int(3)));
+        assert_eq!(
+            run("1 2 3 depth depth")?.stack.last(),
+            Some(&Literal::Int(4))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_dupall() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("1 2 dupall")?.stack,
+            vec![
+                Literal::Int(1),
+                Literal::Int(2),
+                Literal::Int(1),
+                Literal::Int(2)
+            ]
+        );
+        assert!(run("dupall")?.stack.is_empty());
+
+        let mut interpreter = load("1 2 3 dupall")?;
+        interpreter.set_max_stack(Some(5));
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::StackOverflow(5))
+        ));
+        assert_eq!(interpreter.stack.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_rand() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("42 seed rand rand")?.stack,
+            vec![Literal::Number(0.9816471), Literal::Number(0.37027138)]
+        );
+        // reseeding starts the same sequence again, and without a seed every run matches
+        assert_eq!(
+            run("42 seed rand 42 seed rand")?.stack,
+            run("42 seed rand dup")?.stack
+        );
+        assert_eq!(run("rand rand")?.stack, run("rand rand")?.stack);
+
+        let stack = run("0 1000 for do drop rand end")?.stack;
+        assert!(stack
+            .iter()
+            .all(|n| matches!(n, Literal::Number(n) if (0.0..1.0).contains(n))));
+
+        let mut interpreter = load("\"a\" seed")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::String("a".into())]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_arg() -> Result<(), ConstantError> {
+        let mut interpreter = load("1 arg 0 arg")?;
+        interpreter.set_args(vec!["first".into(), "second".into()]);
+        interpreter.interpret()?;
+        assert_eq!(
+            interpreter.stack,
+            vec![
+                Literal::String("second".into()),
+                Literal::String("first".into())
+            ]
+        );
+
+        let mut interpreter = load("0 arg")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_to_number() -> Result<(), ConstantError> {
+        assert_eq!(run("\"42\" to_number")?.stack, vec![Literal::Int(42)]);
+        assert_eq!(
+            run("\"-2.5\" to_number")?.stack,
+            vec![Literal::Number(-2.5)]
+        );
+
+        let mut interpreter = load("\"abc\" to_number")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(e)) if e.contains("abc")
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::String("abc".into())]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_to_bool() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("0 to_bool 5 to_bool -1 to_bool 0.0 to_bool 0.5 to_bool")?.stack,
+            vec![
+                Literal::Bool(false),
+                Literal::Bool(true),
+                Literal::Bool(true),
+                Literal::Bool(false),
+                Literal::Bool(true)
+            ]
+        );
+        assert_eq!(
+            run("\"true\" to_bool \"false\" to_bool false to_bool")?.stack,
+            vec![
+                Literal::Bool(true),
+                Literal::Bool(false),
+                Literal::Bool(false)
+            ]
+        );
+
+        let mut interpreter = load("\"yes\" to_bool")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(e)) if e.contains("yes")
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::String("yes".into())]);
+        assert!(matches!(
+            run("'a' to_bool"),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_to_int() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("true to_int false to_int true to_number")?.stack,
+            vec![Literal::Int(1), Literal::Int(0), Literal::Int(1)]
+        );
+        assert_eq!(
+            run("2.7 to_int -2.7 to_int 3 to_int")?.stack,
+            vec![Literal::Int(2), Literal::Int(-2), Literal::Int(3)]
+        );
+
+        let mut interpreter = load("\"1\" to_int")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::String("1".into())]);
+        assert!(matches!(
+            run("1e30 to_int"),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert!(matches!(
+            run("-1 sqrt to_int"),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_type_checks() -> Result<(), ConstantError> {
+        let checks = |value: &str| -> Result<Vec<Literal>, ConstantError> {
+            ["is_number", "is_string", "is_bool"]
+                .iter()
+                .map(|op| Ok(run(&format!("{value} {op}"))?.stack.remove(1)))
+                .collect()
+        };
+        let bools = |b: [bool; 3]| b.map(Literal::Bool).to_vec();
+
+        assert_eq!(checks("1")?, bools([true, false, false]));
+        assert_eq!(checks("1.5")?, bools([true, false, false]));
+        assert_eq!(checks("\"1\"")?, bools([false, true, false]));
+        assert_eq!(checks("true")?, bools([false, false, true]));
+        assert_eq!(checks("'a'")?, bools([false, false, false]));
+
+        // the value being checked stays on the stack
+        assert_eq!(
+            run("5 is_number")?.stack,
+            vec![Literal::Int(5), Literal::Bool(true)]
+        );
+        for op in ["is_number", "is_string", "is_bool"] {
+            assert!(matches!(
+                run(op),
+                Err(ConstantError::InvalidStackAmount(_, 1))
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_to_string() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("5 to_string \"!\" +")?.stack,
+            vec![Literal::String("5!".into())]
+        );
+        assert_eq!(
+            run("2.5 to_string true to_string \"a\" to_string")?.stack,
+            vec![
+                Literal::String("2.5".into()),
+                Literal::String("true".into()),
+                Literal::String("a".into())
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_len() -> Result<(), ConstantError> {
+        assert_eq!(run("\"héllo\" len")?.stack, vec![Literal::Int(5)]);
+        assert_eq!(run("\"\" len")?.stack, vec![Literal::Int(0)]);
+
+        let mut interpreter = load("5 len")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(5)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_recursion_limit() -> Result<(), ConstantError> {
+        // tests run on threads with the default 2 MB stack, so hitting
+        // the default limit here shows that it's low enough to be safe
+        let mut interpreter = load("proc forever do call forever end call forever")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::RecursionLimitExceeded(
+                DEFAULT_RECURSION_LIMIT
+            ))
+        ));
+        assert_eq!(interpreter.depth, 0);
+
+        // nested loops and ifs count towards the limit as well
+        assert!(matches!(
+            run("proc p do if true do while true do 0 1 for do call p end end end end call p"),
+            Err(ConstantError::RecursionLimitExceeded(
+                DEFAULT_RECURSION_LIMIT
+            ))
+        ));
+
+        let mut interpreter =
+            load("proc down do if dup 0 > do 1 - call down end end 100 call down")?;
+        interpreter.interpret()?;
+        assert_eq!(interpreter.stack, vec![Literal::Int(0)]);
+
+        let mut interpreter = load("proc forever do call forever end call forever")?;
+        interpreter.set_recursion_limit(50);
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::RecursionLimitExceeded(50))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_for() -> Result<(), ConstantError> {
+        assert_eq!(run_output("0 3 for do print end")?, "0\n1\n2\n");
+        assert_eq!(run("0 5 for do end")?.stack.len(), 5);
+        assert_eq!(run_output("3 3 for do print end")?, "");
+        assert_eq!(run_output("5 2 for do print end")?, "");
+
+        let mut interpreter = load("0 2.5 for do end")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(0), Literal::Number(2.5)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_break_continue() -> Result<(), ConstantError> {
+        let source = "0 bind x
+            while true do
+                x 1 + bind x
+                if x 5 == do break end
+                if x 2 % 0 == do continue end
+                x print
+            end";
+        assert_eq!(run_output(source)?, "1\n3\n");
+
+        let source = "0 10 for do
+                if dup 3 == do drop break end
+                print
+            end";
+        assert_eq!(run_output(source)?, "0\n1\n2\n");
+
+        assert!(matches!(run("break"), Err(ConstantError::OutsideLoop(_))));
+        assert!(matches!(
+            run("if true do continue end"),
+            Err(ConstantError::OutsideLoop(_))
+        ));
+        assert!(matches!(
+            run("proc p do break end while true do call p end"),
+            Err(ConstantError::OutsideLoop(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_select() -> Result<(), ConstantError> {
+        assert_eq!(run("true 1 2 select")?.stack, vec![Literal::Int(1)]);
+        assert_eq!(run("false 1 2 select")?.stack, vec![Literal::Int(2)]);
+
+        let mut interpreter = load("0 1 2 select")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::Int(0), Literal::Int(1), Literal::Int(2)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_pick() -> Result<(), ConstantError> {
+        assert_eq!(run("1 2 3 0 pick")?.stack.last(), Some(&Literal::Int(3)));
+        assert_eq!(
+            run("1 2 3 2 pick")?.stack,
+            vec![
+                Literal::Int(1),
+                Literal::Int(2),
+                Literal::Int(3),
+                Literal::Int(1)
+            ]
+        );
+
+        let mut interpreter = load("1 2 3 3 pick")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidStackAmount(_, 5))
+        ));
+        assert_eq!(interpreter.stack.len(), 4);
+
+        for source in ["1 -1 pick", "1 0.5 pick"] {
+            let mut interpreter = load(source)?;
+            assert!(matches!(
+                interpreter.interpret(),
+                Err(ConstantError::InvalidStackAmount(_, 2))
+            ));
+            assert_eq!(interpreter.stack.len(), 2);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_proc_stack_effect() -> Result<(), ConstantError> {
+        assert_eq!(
+            run("proc add3 ( a b c -- sum ) do + + end 1 2 3 call add3")?.stack,
+            vec![Literal::Int(6)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_proc_scope() -> Result<(), ConstantError> {
+        let source = "1 bind x
+            proc p do
+                2 bind x
+                3 bind y
+                x print
+            end
+            call p
+            x print";
+        assert_eq!(run_output(source)?, "2\n1\n");
+
+        assert!(matches!(
+            run("proc p do 3 bind y end call p y"),
+            Err(ConstantError::IdentDoesNotExist(_))
+        ));
+        assert_eq!(
+            run("5 bind x proc p do x 1 + end call p")?.stack,
+            vec![Literal::Int(6)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_proc_before_definition() -> Result<(), ConstantError> {
+        let source = "5 call is_even print
+            proc is_even do
+                if dup 0 == do drop true else do 1 - call is_odd end
+            end
+            proc is_odd do
+                if dup 0 == do drop false else do 1 - call is_even end
+            end";
+        assert_eq!(run_output(source)?, "false\n");
+
+        let mut interpreter = load("1 bind p call p proc p do end")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::ProcDoesNotExist(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_max_stack() -> Result<(), ConstantError> {
+        let mut interpreter = load("while true do 1 end")?;
+        interpreter.set_max_stack(Some(100));
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::StackOverflow(100))
+        ));
+        assert_eq!(interpreter.stack.len(), 100);
+
+        let mut interpreter = load("1 2 over")?;
+        interpreter.set_max_stack(Some(2));
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::StackOverflow(2))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(1), Literal::Int(2)]);
+
+        let mut interpreter = load("1 2 + 3")?;
+        interpreter.set_max_stack(Some(2));
+        interpreter.interpret()?;
+
+        let mut interpreter = load("\"a,b,c\" \",\" split")?;
+        interpreter.set_max_stack(Some(3));
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::StackOverflow(3))
+        ));
+        assert_eq!(
+            interpreter.stack,
+            vec![Literal::String("a,b,c".into()), Literal::String(",".into())]
+        );
+
+        let mut interpreter = load("\"a,b,c\" \",\" split")?;
+        interpreter.set_max_stack(Some(4));
+        interpreter.interpret()?;
+        assert_eq!(interpreter.stack.len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpret_not() -> Result<(), ConstantError> {
+        assert_eq!(run("true not")?.stack, vec![Literal::Bool(false)]);
+        assert_eq!(run("false not")?.stack, vec![Literal::Bool(true)]);
+
+        let mut interpreter = load("5 not")?;
+        assert!(matches!(
+            interpreter.interpret(),
+            Err(ConstantError::InvalidOperation(_))
+        ));
+        assert_eq!(interpreter.stack, vec![Literal::Int(5)]);
+
+        Ok(())
     }
 }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use lazy_static::lazy_static;
-pub use token::{Literal, Token, TokenType};
+pub use token::{Literal, Span, Token, TokenType};
 
 use crate::error::ConstantError;
 
@@ -16,11 +16,98 @@ lazy_static! {
         h.insert(String::from("dup"), TokenType::Dup);
         h.insert(String::from("swap"), TokenType::Swap);
         h.insert(String::from("drop"), TokenType::Drop);
+        h.insert(String::from("rot"), TokenType::Rot);
+        h.insert(String::from("over"), TokenType::Over);
+        h.insert(String::from("clear"), TokenType::Clear);
+        h.insert(String::from("depth"), TokenType::Depth);
+        h.insert(String::from("read"), TokenType::Read);
+        // word forms of the arithmetic symbols
+        h.insert(String::from("add"), TokenType::Plus);
+        h.insert(String::from("sub"), TokenType::Minus);
+        h.insert(String::from("mul"), TokenType::Asterisk);
+        h.insert(String::from("div"), TokenType::Slash);
+        h.insert(String::from("mod"), TokenType::Percent);
+        h.insert(String::from("and"), TokenType::And);
+        h.insert(String::from("or"), TokenType::Or);
+        h.insert(String::from("not"), TokenType::Not);
+        h.insert(String::from("arg"), TokenType::Arg);
+        h.insert(String::from("to_number"), TokenType::ToNumber);
+        h.insert(String::from("to_string"), TokenType::ToString);
+        h.insert(String::from("len"), TokenType::Len);
+        h.insert(String::from("write"), TokenType::Write);
+        h.insert(String::from("select"), TokenType::Select);
+        h.insert(String::from("pick"), TokenType::Pick);
+        h.insert(String::from("band"), TokenType::BAnd);
+        h.insert(String::from("bor"), TokenType::BOr);
+        h.insert(String::from("bxor"), TokenType::BXor);
+        h.insert(String::from("shl"), TokenType::Shl);
+        h.insert(String::from("shr"), TokenType::Shr);
+        h.insert(String::from("sqrt"), TokenType::Sqrt);
+        h.insert(String::from("abs"), TokenType::Abs);
+        h.insert(String::from("floor"), TokenType::Floor);
+        h.insert(String::from("ceil"), TokenType::Ceil);
+        h.insert(String::from("round"), TokenType::Round);
+        h.insert(String::from("min"), TokenType::Min);
+        h.insert(String::from("max"), TokenType::Max);
+        h.insert(String::from("neg"), TokenType::Neg);
+        h.insert(String::from("pow"), TokenType::Pow);
+        h.insert(String::from("char_at"), TokenType::CharAt);
+        h.insert(String::from("substring"), TokenType::Substring);
+        h.insert(String::from("to_upper"), TokenType::ToUpper);
+        h.insert(String::from("to_lower"), TokenType::ToLower);
+        h.insert(String::from("split"), TokenType::Split);
+        h.insert(String::from("list_new"), TokenType::ListNew);
+        h.insert(String::from("list_push"), TokenType::ListPush);
+        h.insert(String::from("list_len"), TokenType::ListLen);
+        h.insert(String::from("2dup"), TokenType::TwoDup);
+        h.insert(String::from("2drop"), TokenType::TwoDrop);
+        h.insert(String::from("nip"), TokenType::Nip);
+        h.insert(String::from("tuck"), TokenType::Tuck);
+        h.insert(String::from("assert"), TokenType::Assert);
+        h.insert(String::from("exit"), TokenType::Exit);
+        h.insert(String::from("dump"), TokenType::Dump);
+        h.insert(String::from("ord"), TokenType::Ord);
+        h.insert(String::from("chr"), TokenType::Chr);
+        h.insert(String::from("idiv"), TokenType::IDiv);
+        h.insert(String::from("apply"), TokenType::Apply);
+        h.insert(String::from("times"), TokenType::Times);
+        h.insert(String::from("rotd"), TokenType::ReverseRot);
+        h.insert(String::from("-rot"), TokenType::ReverseRot);
+        h.insert(String::from("format"), TokenType::Format);
+        h.insert(String::from("input"), TokenType::Input);
+        h.insert(String::from("roll_up"), TokenType::RollUp);
+        h.insert(String::from("roll_down"), TokenType::RollDown);
+        h.insert(String::from("roll"), TokenType::Roll);
+        h.insert(String::from("contains"), TokenType::Contains);
+        h.insert(String::from("starts_with"), TokenType::StartsWith);
+        h.insert(String::from("ends_with"), TokenType::EndsWith);
+        h.insert(String::from("replace"), TokenType::Replace);
+        h.insert(String::from("trim"), TokenType::Trim);
+        h.insert(String::from("trim_start"), TokenType::TrimStart);
+        h.insert(String::from("trim_end"), TokenType::TrimEnd);
+        h.insert(String::from("reverse"), TokenType::Reverse);
+        h.insert(String::from("dupall"), TokenType::DupAll);
+        h.insert(String::from("to_bool"), TokenType::ToBool);
+        h.insert(String::from("to_int"), TokenType::ToInt);
+        h.insert(String::from("is_number"), TokenType::IsNumber);
+        h.insert(String::from("is_string"), TokenType::IsString);
+        h.insert(String::from("is_bool"), TokenType::IsBool);
+        h.insert(String::from("rand"), TokenType::Rand);
+        h.insert(String::from("seed"), TokenType::Seed);
         h.insert(String::from("bind"), TokenType::Bind);
+        h.insert(String::from("const"), TokenType::Const);
+        h.insert(String::from("unbind"), TokenType::Unbind);
+        h.insert(String::from("defined"), TokenType::Defined);
         h.insert(String::from("if"), TokenType::If);
         h.insert(String::from("elif"), TokenType::Elif);
         h.insert(String::from("else"), TokenType::Else);
+        h.insert(String::from("cond"), TokenType::Cond);
+        h.insert(String::from("case"), TokenType::Case);
         h.insert(String::from("while"), TokenType::While);
+        h.insert(String::from("for"), TokenType::For);
+        h.insert(String::from("repeat"), TokenType::Repeat);
+        h.insert(String::from("break"), TokenType::Break);
+        h.insert(String::from("continue"), TokenType::Continue);
         h.insert(String::from("proc"), TokenType::Proc);
         h.insert(String::from("call"), TokenType::Call);
         h.insert(String::from("do"), TokenType::Do);
@@ -33,20 +120,27 @@ pub struct Lexer {
     source: Vec<char>,
     current_char: char,
     current_pos: usize,
+    line: usize,
+    column: usize,
+    // the types of the last two tokens, a stack effect can only follow 'proc <name>'
+    previous: [TokenType; 2],
 }
 
 impl Lexer {
     pub fn new(source: &str) -> Self {
+        // the source always ends in a '\0' so there is a current character
+        // even when it's empty, and the lexer knows where to stop
         let mut source = source.chars().collect::<Vec<char>>();
-        if source.last() != Some(&'\0') {
-            source.push('\0');
-        }
+        source.push('\0');
         let current_char = source[0];
 
         Self {
             source,
             current_char,
             current_pos: 0,
+            line: 1,
+            column: 1,
+            previous: [TokenType::EOF; 2],
         }
     }
 
@@ -75,6 +169,14 @@ impl Lexer {
 
     fn next(&mut self) {
         if let Some(&c) = self.source.get(self.current_pos + 1) {
+            // "\r\n" only moves to the next line once it gets past the '\n',
+            // but a '\r' on its own still ends a line
+            if self.current_char == '\n' || (self.current_char == '\r' && c != '\n') {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
             self.current_pos += 1;
             self.current_char = c;
         }
@@ -84,17 +186,165 @@ impl Lexer {
         *self.source.get(self.current_pos + 1).unwrap_or(&'\0')
     }
 
+    // lexes the digits of a number literal starting at the current character,
+    // start_pos allows a leading sign to be included in the literal
+    // literals without a decimal point are integers
+    fn number(&mut self, start_pos: usize) -> Result<Token, ConstantError> {
+        let column = self.column - (self.current_pos - start_pos);
+
+        // hexadecimal and binary literals start with 0x and 0b
+        let radix = match (self.current_char, self.peek()) {
+            ('0', 'x' | 'X') => Some(16),
+            ('0', 'b' | 'B') => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            self.next();
+            self.next();
+
+            let digits_pos = self.current_pos;
+            while self.current_char.is_ascii_alphanumeric() || self.current_char == '_' {
+                self.next();
+            }
+
+            let text = self.source[start_pos..self.current_pos]
+                .iter()
+                .collect::<String>();
+            if !Self::valid_separators(&self.source[digits_pos..self.current_pos], radix) {
+                return Err(ConstantError::InvalidString(text, self.line, column));
+            }
+            let digits = self.source[digits_pos..self.current_pos]
+                .iter()
+                .filter(|&&c| c != '_')
+                .collect::<String>();
+            let sign = if self.source[start_pos] == '-' {
+                "-"
+            } else {
+                ""
+            };
+
+            return match i64::from_str_radix(&format!("{sign}{digits}"), radix) {
+                Ok(num) => Ok(Token::new(TokenType::Number, text, Some(Literal::Int(num)))),
+                Err(_) => Err(ConstantError::InvalidString(text, self.line, column)),
+            };
+        }
+
+        while self.current_char.is_ascii_digit() || self.current_char == '_' {
+            self.next();
+        }
+
+        let mut is_float = false;
+        if self.current_char == '.' {
+            is_float = true;
+            self.next();
+
+            while self.current_char.is_ascii_digit() || self.current_char == '_' {
+                self.next();
+            }
+        }
+
+        // an exponent like e3 or E-4 also makes the literal a float
+        let mut missing_exponent = false;
+        if matches!(self.current_char, 'e' | 'E') {
+            is_float = true;
+            self.next();
+            if matches!(self.current_char, '+' | '-') {
+                self.next();
+            }
+
+            missing_exponent = !self.current_char.is_ascii_digit();
+            while self.current_char.is_ascii_digit() || self.current_char == '_' {
+                self.next();
+            }
+        }
+
+        let text = self.source[start_pos..self.current_pos]
+            .iter()
+            .collect::<String>();
+        if missing_exponent
+            || !Self::valid_separators(&self.source[start_pos..self.current_pos], 10)
+        {
+            return Err(ConstantError::InvalidString(text, self.line, column));
+        }
+        let digits = text.replace('_', "");
+
+        let literal = if is_float {
+            match digits.parse::<f32>() {
+                Ok(num) if num.is_finite() => Literal::Number(num),
+                // the exponent is too large for a float
+                _ => return Err(ConstantError::InvalidString(text, self.line, column)),
+            }
+        } else if let Ok(num) = digits.parse::<i64>() {
+            Literal::Int(num)
+        } else {
+            // the literal is too large to fit in an integer
+            return Err(ConstantError::InvalidString(text, self.line, column));
+        };
+
+        Ok(Token::new(TokenType::Number, text, Some(literal)))
+    }
+
+    // underscores can separate digits, but can't lead, trail, or be doubled up
+    fn valid_separators(chars: &[char], radix: u32) -> bool {
+        chars.iter().enumerate().all(|(i, &c)| {
+            c != '_'
+                || (i > 0
+                    && chars[i - 1].is_digit(radix)
+                    && chars.get(i + 1).is_some_and(|n| n.is_digit(radix)))
+        })
+    }
+
+    // translates the escape sequence starting at the current '\\',
+    // leaving the lexer on the escaped character
+    fn escape(&mut self) -> Result<char, ConstantError> {
+        self.next();
+        match self.current_char {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '\'' => Ok('\''),
+            '\0' => Err(ConstantError::StringNotTerminated),
+            c => Err(ConstantError::InvalidEscape(c)),
+        }
+    }
+
     pub fn next_token(&mut self) -> Result<Token, ConstantError> {
         while self.skip_comments() || self.skip_whitespace() {}
 
-        match self.current_char {
+        // tokens, and errors about invalid sequences, are positioned at their first character
+        let (line, column, start_pos) = (self.line, self.column, self.current_pos);
+
+        let mut token = match self.current_char {
             '+' => {
                 self.next();
                 Ok(Token::new(TokenType::Plus, '+'.into(), None))
             }
             '-' => {
-                self.next();
-                Ok(Token::new(TokenType::Minus, '-'.into(), None))
+                // a '-' directly in front of a digit at the start of a
+                // token is the sign of a number rather than a subtraction
+                let at_boundary =
+                    self.current_pos == 0 || self.source[self.current_pos - 1].is_whitespace();
+                // as are keywords that start with one, like '-rot'
+                let word = self.source[self.current_pos..]
+                    .iter()
+                    .enumerate()
+                    .take_while(|(i, c)| *i == 0 || c.is_alphanumeric() || **c == '_')
+                    .map(|(_, c)| c)
+                    .collect::<String>();
+
+                if at_boundary && self.peek().is_ascii_digit() {
+                    self.next();
+                    self.number(start_pos)
+                } else if let Some(&tt) = KEYWORDS.get(&word).filter(|_| at_boundary) {
+                    for _ in word.chars() {
+                        self.next();
+                    }
+                    Ok(Token::new(tt, word, None))
+                } else {
+                    self.next();
+                    Ok(Token::new(TokenType::Minus, '-'.into(), None))
+                }
             }
             '*' => {
                 self.next();
@@ -108,6 +358,14 @@ impl Lexer {
                 self.next();
                 Ok(Token::new(TokenType::Percent, '%'.into(), None))
             }
+            '[' => {
+                self.next();
+                Ok(Token::new(TokenType::LBracket, '['.into(), None))
+            }
+            ']' => {
+                self.next();
+                Ok(Token::new(TokenType::RBracket, ']'.into(), None))
+            }
             '>' => {
                 self.next();
                 Ok(if self.current_char == '=' {
@@ -118,7 +376,8 @@ impl Lexer {
                 } else {
                     return Err(ConstantError::InvalidString(
                         format!(">{}", self.current_char),
-                        self.current_pos,
+                        line,
+                        column,
                     ));
                 })
             }
@@ -132,7 +391,8 @@ impl Lexer {
                 } else {
                     return Err(ConstantError::InvalidString(
                         format!("<{}", self.current_char),
-                        self.current_pos - 1,
+                        line,
+                        column,
                     ));
                 })
             }
@@ -144,7 +404,8 @@ impl Lexer {
                 } else {
                     Err(ConstantError::InvalidString(
                         format!("={}", self.current_char),
-                        self.current_pos - 1,
+                        line,
+                        column,
                     ))
                 }
             }
@@ -156,7 +417,8 @@ impl Lexer {
                 } else {
                     Err(ConstantError::InvalidString(
                         format!("!{}", self.current_char),
-                        self.current_pos - 1,
+                        line,
+                        column,
                     ))
                 }
             }
@@ -168,7 +430,8 @@ impl Lexer {
                 } else {
                     Err(ConstantError::InvalidString(
                         format!("&{}", self.current_char),
-                        self.current_pos - 1,
+                        line,
+                        column,
                     ))
                 }
             }
@@ -180,41 +443,53 @@ impl Lexer {
                 } else {
                     Err(ConstantError::InvalidString(
                         format!("|{}", self.current_char),
-                        self.current_pos - 1,
+                        line,
+                        column,
                     ))
                 }
             }
             '0'..='9' => {
-                let start_pos = self.current_pos;
-                while self.current_char.is_numeric() {
-                    self.next();
-                }
-
-                if self.current_char == '.' {
-                    self.next();
+                // a few keywords, like '2dup', start with a digit
+                let word = self.source[self.current_pos..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .collect::<String>();
 
-                    while self.current_char.is_numeric() {
+                if let Some(&tt) = KEYWORDS.get(&word) {
+                    for _ in word.chars() {
                         self.next();
                     }
+                    Ok(Token::new(tt, word, None))
+                } else {
+                    self.number(self.current_pos)
+                }
+            }
+            // a stack effect annotation, like '( a b -- sum )', is kept whole
+            '(' if self.previous == [TokenType::Proc, TokenType::Ident] => {
+                // an unclosed annotation is reported up to the end of its line
+                while !matches!(self.current_char, ')' | '\0' | '\n' | '\r') {
+                    self.next();
                 }
 
-                let text = self.source[start_pos..self.current_pos]
-                    .iter()
-                    .collect::<String>();
-
-                let num = text.parse::<f32>().unwrap();
+                let text = self.source[start_pos..self.current_pos].iter().collect();
+                if self.current_char != ')' {
+                    return Err(ConstantError::InvalidString(text, line, column));
+                }
 
-                Ok(Token::new(
-                    TokenType::Number,
-                    text,
-                    Some(Literal::Number(num)),
-                ))
+                self.next();
+                Ok(Token::new(TokenType::StackEffect, format!("{text})"), None))
             }
             '"' => {
                 self.next();
                 let start_pos = self.current_pos;
+                let mut text = String::new();
 
                 while self.current_char != '"' && self.current_char != '\0' {
+                    if self.current_char == '\\' {
+                        text.push(self.escape()?);
+                    } else {
+                        text.push(self.current_char);
+                    }
                     self.next();
                 }
 
@@ -222,9 +497,6 @@ impl Lexer {
                     return Err(ConstantError::StringNotTerminated);
                 }
 
-                let text = self.source[start_pos..self.current_pos]
-                    .iter()
-                    .collect::<String>();
                 let tok = Token::new(
                     TokenType::String,
                     self.source[(start_pos - 1)..=self.current_pos]
@@ -237,6 +509,31 @@ impl Lexer {
 
                 Ok(tok)
             }
+            // chars hold exactly one character, or one escape sequence
+            '\'' => {
+                let start_pos = self.current_pos;
+                self.next();
+
+                let c = match self.current_char {
+                    '\\' => Some(self.escape()?),
+                    '\'' | '\0' => None,
+                    c => Some(c),
+                };
+                if c.is_some() {
+                    self.next();
+                }
+
+                // the text includes the character after the literal, unless that's the '\0' at the end
+                let end_pos = (self.current_pos + 1).min(self.source.len() - 1);
+                let text = self.source[start_pos..end_pos].iter().collect::<String>();
+                match c {
+                    Some(c) if self.current_char == '\'' => {
+                        self.next();
+                        Ok(Token::new(TokenType::Char, text, Some(Literal::Char(c))))
+                    }
+                    _ => Err(ConstantError::InvalidString(text, line, column)),
+                }
+            }
             'a'..='z' | 'A'..='Z' => {
                 let start_pos = self.current_pos;
 
@@ -260,9 +557,19 @@ impl Lexer {
             '\0' => Ok(Token::eof()),
             _ => Err(ConstantError::InvalidString(
                 format!("{}", self.current_char),
-                self.current_pos,
+                line,
+                column,
             )),
-        }
+        }?;
+
+        token.span = Span {
+            line,
+            column,
+            len: self.current_pos - start_pos,
+        };
+        self.previous = [self.previous[1], token.token_type];
+
+        Ok(token)
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<Token>, ConstantError> {
@@ -272,8 +579,8 @@ impl Lexer {
             tokens.push(self.next_token()?);
         }
 
-        if tokens.last() != Some(&Token::eof()) {
-            tokens.push(Token::eof());
+        if tokens.last().map(|t| t.token_type) != Some(TokenType::EOF) {
+            tokens.push(self.next_token()?);
         }
 
         Ok(tokens)
@@ -299,15 +606,124 @@ mod tests {
 
     #[test]
     fn lexer_next_token_number() -> Result<(), ConstantError> {
-        let mut l = Lexer::new("123.456 123");
+        let mut l = Lexer::new("123.456 123 123.");
 
         assert_eq!(l.next_token()?.literal.unwrap(), Literal::Number(123.456));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(123));
         assert_eq!(l.next_token()?.literal.unwrap(), Literal::Number(123.0));
         assert_eq!(l.next_token()?.token_type, TokenType::EOF);
 
         Ok(())
     }
 
+    #[test]
+    fn lexer_next_token_negative_number() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("-5 -2.5 - 3");
+
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(-5));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Number(-2.5));
+        assert_eq!(l.next_token()?.token_type, TokenType::Minus);
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(3));
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_next_token_int_overflow() {
+        assert!(matches!(
+            Lexer::new("1 99999999999999999999").tokenize(),
+            Err(ConstantError::InvalidString(_, 1, 3))
+        ));
+    }
+
+    #[test]
+    fn lexer_next_token_radix() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("0xFF 0Xff 0b1010 -0x10 0");
+
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(255));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(255));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(10));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(-16));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(0));
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        for source in ["0x", "0b2", "0xFG", "0b"] {
+            assert!(matches!(
+                Lexer::new(source).tokenize(),
+                Err(ConstantError::InvalidString(s, 1, 1)) if s == source
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_next_token_digit_keyword() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("2dup 2 dup 2drop");
+
+        assert_eq!(l.next_token()?.token_type, TokenType::TwoDup);
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(2));
+        assert_eq!(l.next_token()?.token_type, TokenType::Dup);
+        assert_eq!(l.next_token()?.token_type, TokenType::TwoDrop);
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        let mut l = Lexer::new("-rot - rot -1");
+
+        assert_eq!(l.next_token()?.token_type, TokenType::ReverseRot);
+        assert_eq!(l.next_token()?.token_type, TokenType::Minus);
+        assert_eq!(l.next_token()?.token_type, TokenType::Rot);
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(-1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_next_token_exponent() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("1e3 2.5e-4 6.022E23 -1e+2");
+
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Number(1000.0));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Number(0.00025));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Number(6.022e23));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Number(-100.0));
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        for source in ["5e", "5e-", "5e+x", "1e99", "1_e5"] {
+            assert!(
+                matches!(
+                    Lexer::new(source).tokenize(),
+                    Err(ConstantError::InvalidString(..))
+                ),
+                "{source}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_next_token_separators() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("1_000_000 1.234_56 -1_0 0xFF_FF");
+
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(1_000_000));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Number(1.23456));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(-10));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Int(0xFFFF));
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        for source in ["_5", "5_", "5__0", "5_.0", "5._0", "0x_FF"] {
+            assert!(
+                matches!(
+                    Lexer::new(source).tokenize(),
+                    Err(ConstantError::InvalidString(..))
+                ),
+                "{source}"
+            );
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn lexer_next_token_string() -> Result<(), ConstantError> {
         let mut l = Lexer::new("\"this is a test string\" \"this is another test string\"");
@@ -325,6 +741,105 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn lexer_next_token_string_escape() -> Result<(), ConstantError> {
+        let mut l = Lexer::new(r#""a\nb" "\t\\\"""#);
+
+        assert_eq!(
+            l.next_token()?.literal.unwrap(),
+            Literal::String("a\nb".into())
+        );
+        assert_eq!(
+            l.next_token()?.literal.unwrap(),
+            Literal::String("\t\\\"".into())
+        );
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        assert!(matches!(
+            Lexer::new(r#""a\q""#).tokenize(),
+            Err(ConstantError::InvalidEscape('q'))
+        ));
+        assert!(matches!(
+            Lexer::new(r#""a\"#).tokenize(),
+            Err(ConstantError::StringNotTerminated)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_next_token_char() -> Result<(), ConstantError> {
+        let mut l = Lexer::new(r"'a' '\n' '\'' 'é'");
+
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Char('a'));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Char('\n'));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Char('\''));
+        assert_eq!(l.next_token()?.literal.unwrap(), Literal::Char('é'));
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        for source in ["'ab'", "''", "'a", "'"] {
+            assert!(
+                matches!(
+                    Lexer::new(source).tokenize(),
+                    Err(ConstantError::InvalidString(..))
+                ),
+                "{source}"
+            );
+        }
+
+        let err = Lexer::new("'a").tokenize().unwrap_err();
+        assert!(matches!(&err, ConstantError::InvalidString(text, 1, 1) if text == "'a"));
+        assert_eq!(err.to_string(), "Invalid string ''a' at line 1, column 1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_next_token_brackets() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("[1]");
+
+        assert_eq!(l.next_token()?.token_type, TokenType::LBracket);
+        assert_eq!(l.next_token()?.token_type, TokenType::Number);
+        assert_eq!(l.next_token()?.token_type, TokenType::RBracket);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_next_token_stack_effect() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("proc p ( a b -- c ) do");
+
+        assert_eq!(l.next_token()?.token_type, TokenType::Proc);
+        assert_eq!(l.next_token()?.token_type, TokenType::Ident);
+        let tok = l.next_token()?;
+        assert_eq!(tok.token_type, TokenType::StackEffect);
+        assert_eq!(tok.lexeme, "( a b -- c )");
+        assert_eq!(l.next_token()?.token_type, TokenType::Do);
+
+        // a '(' anywhere else is just an invalid character
+        for source in [
+            "1 ( a -- a ) dup",
+            "proc ( a -- a )",
+            "proc p do ( a -- a ) end",
+        ] {
+            assert!(
+                matches!(
+                    Lexer::new(source).tokenize(),
+                    Err(ConstantError::InvalidString(text, ..)) if text == "("
+                ),
+                "{source}"
+            );
+        }
+
+        // an unclosed annotation doesn't swallow the rest of the program into its error
+        assert!(matches!(
+            Lexer::new("proc p ( a\ndo 1 end").tokenize(),
+            Err(ConstantError::InvalidString(text, 1, 8)) if text == "( a"
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn lexer_next_token_bool() -> Result<(), ConstantError> {
         let mut l = Lexer::new("true false true");
@@ -350,6 +865,159 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn lexer_next_token_arithmetic_words() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("add sub mul div mod");
+
+        assert_eq!(l.next_token()?.token_type, TokenType::Plus);
+        assert_eq!(l.next_token()?.token_type, TokenType::Minus);
+        assert_eq!(l.next_token()?.token_type, TokenType::Asterisk);
+        assert_eq!(l.next_token()?.token_type, TokenType::Slash);
+        assert_eq!(l.next_token()?.token_type, TokenType::Percent);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_next_token_logical() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("and or && ||");
+
+        assert_eq!(l.next_token()?.token_type, TokenType::And);
+        assert_eq!(l.next_token()?.token_type, TokenType::Or);
+        assert_eq!(l.next_token()?.token_type, TokenType::And);
+        assert_eq!(l.next_token()?.token_type, TokenType::Or);
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_span_len() -> Result<(), ConstantError> {
+        let lens = Lexer::new("\"a\\tb\" 0xFF 'x' -rot -1_000 end")
+            .tokenize()?
+            .iter()
+            .map(|t| t.span.len)
+            .collect::<Vec<usize>>();
+
+        assert_eq!(lens, vec![6, 4, 3, 4, 6, 3, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_position() -> Result<(), ConstantError> {
+        let mut l = Lexer::new("1 2\n  dup\n\nprint");
+
+        let tok = l.next_token()?;
+        assert_eq!((tok.span.line, tok.span.column), (1, 1));
+        let tok = l.next_token()?;
+        assert_eq!((tok.span.line, tok.span.column), (1, 3));
+        let tok = l.next_token()?;
+        assert_eq!((tok.span.line, tok.span.column), (2, 3));
+        let tok = l.next_token()?;
+        assert_eq!((tok.span.line, tok.span.column), (4, 1));
+
+        let mut l = Lexer::new("1\n  -2 -rot");
+        l.next_token()?;
+        let tok = l.next_token()?;
+        assert_eq!((tok.span.line, tok.span.column), (2, 3));
+        let tok = l.next_token()?;
+        assert_eq!((tok.span.line, tok.span.column), (2, 6));
+
+        assert!(matches!(
+            Lexer::new("1\n 2 =x").tokenize(),
+            Err(ConstantError::InvalidString(_, 2, 4))
+        ));
+        assert!(matches!(
+            Lexer::new("1 >x").tokenize(),
+            Err(ConstantError::InvalidString(_, 1, 3))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_tokenize_empty() -> Result<(), ConstantError> {
+        for source in ["", "   \n\t  ", "// only a comment", "// one\n  // two\n"] {
+            let tokens = Lexer::new(source).tokenize()?;
+            assert_eq!(tokens.len(), 1, "{source:?}");
+            assert_eq!(tokens[0].token_type, TokenType::EOF);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_tokenize_comments() -> Result<(), ConstantError> {
+        let types = |source| -> Result<Vec<TokenType>, ConstantError> {
+            Ok(Lexer::new(source)
+                .tokenize()?
+                .iter()
+                .map(|t| t.token_type)
+                .collect())
+        };
+        let expected = vec![TokenType::Number, TokenType::Number, TokenType::EOF];
+
+        assert_eq!(types("5 6 // comment")?, expected);
+        assert_eq!(types("5 6 // comment\n   ")?, expected);
+        assert_eq!(types("5 6   \n\t")?, expected);
+        assert_eq!(types("5 // between\n// another\n6")?, expected);
+        assert_eq!(types("// leading\n5\n  6 //")?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_tokenize_comment_in_string() -> Result<(), ConstantError> {
+        // '//' inside quotes is part of the string, not the start of a comment
+        let tokens = Lexer::new("\"http://example.com\" // comment").tokenize()?;
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            tokens[0].literal,
+            Some(Literal::String("http://example.com".into()))
+        );
+
+        let tokens = Lexer::new("\"a // b\" 1").tokenize()?;
+        assert_eq!(tokens[0].literal, Some(Literal::String("a // b".into())));
+        assert_eq!(tokens[1].token_type, TokenType::Number);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_invalid_string_position() {
+        for (source, expected) in [
+            ("1 =x", "=x"),
+            ("1 !x", "!x"),
+            ("1 <x", "<x"),
+            ("1 >x", ">x"),
+            ("1 &x", "&x"),
+            ("1 |x", "|x"),
+            ("1 @", "@"),
+        ] {
+            match Lexer::new(source).tokenize() {
+                Err(ConstantError::InvalidString(text, 1, 3)) => assert_eq!(text, expected),
+                res => panic!("{source:?} gave {res:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn lexer_position_crlf() -> Result<(), ConstantError> {
+        let tokens = Lexer::new("1\r\n2 3\r\n\r\n4\r5").tokenize()?;
+        let positions = tokens
+            .iter()
+            .map(|t| (t.span.line, t.span.column))
+            .collect::<Vec<(usize, usize)>>();
+
+        assert_eq!(
+            positions,
+            vec![(1, 1), (2, 1), (2, 3), (4, 1), (5, 1), (5, 2)]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn lexer_skip_comments() -> Result<(), ConstantError> {
         let mut l = Lexer::new("// this is a comment");
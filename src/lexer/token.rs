@@ -1,15 +1,35 @@
 use std::{
+    cmp::Ordering,
     fmt::Display,
     ops::{Add, Div, Mul, Rem, Sub},
 };
 
-use crate::error::ConstantError;
+use crate::{error::ConstantError, parser::Statement};
+
+// the longest string, in bytes, that multiplying a string can make
+const MAX_REPEAT_LEN: usize = 1 << 30;
+
+// where a token is in the source, its line and column are where it starts
+// and its length is how many characters of the source it covers
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Literal>,
+    pub span: Span,
 }
 
 impl Token {
@@ -18,6 +38,7 @@ impl Token {
             token_type,
             lexeme,
             literal,
+            span: Span::default(),
         }
     }
 
@@ -26,10 +47,12 @@ impl Token {
             token_type: TokenType::EOF,
             lexeme: "".into(),
             literal: None,
+            span: Span::default(),
         }
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
 pub enum TokenType {
     // operations
@@ -46,10 +69,12 @@ pub enum TokenType {
     NotEq,
     And,
     Or,
+    Not,
 
     // data types
     Number,
     String,
+    Char,
     Bool,
 
     // built-ins
@@ -57,34 +82,210 @@ pub enum TokenType {
     Dup,
     Swap,
     Drop,
+    Rot,
+    Over,
+    Clear,
+    Depth,
+    Arg,
+    Read,
+    ToNumber,
+    ToString,
+    Len,
+    Write,
+    Select,
+    Pick,
+    BAnd,
+    BOr,
+    BXor,
+    Shl,
+    Shr,
+    Sqrt,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+    Neg,
+    Pow,
+    CharAt,
+    Substring,
+    ToUpper,
+    ToLower,
+    Split,
+    ListNew,
+    ListPush,
+    ListLen,
+    TwoDup,
+    TwoDrop,
+    Nip,
+    Tuck,
+    Assert,
+    Exit,
+    Dump,
+    Ord,
+    Chr,
+    IDiv,
+    Apply,
+    Times,
+    ReverseRot,
+    Format,
+    Input,
+    RollUp,
+    RollDown,
+    Roll,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Replace,
+    Trim,
+    TrimStart,
+    TrimEnd,
+    Reverse,
+    DupAll,
+    ToBool,
+    ToInt,
+    IsNumber,
+    IsString,
+    IsBool,
+    Rand,
+    Seed,
     Bind,
+    Const,
+    Unbind,
+    Defined,
     If,
     Elif,
     Else,
+    Cond,
+    Case,
     While,
+    For,
+    Repeat,
+    Break,
+    Continue,
     Proc,
     Call,
     Do,
     End,
 
     Ident,
+    StackEffect,
+    LBracket,
+    RBracket,
 
     EOF,
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
+    Int(i64),
     Number(f32),
     String(String),
+    Char(char),
     Bool(bool),
+    List(Vec<Literal>),
+    Block(Vec<Statement>),
+}
+
+impl Literal {
+    // the value of a numeric literal as a float, integers are promoted
+    pub fn as_number(&self) -> Option<f32> {
+        match self {
+            Self::Int(v) => Some(*v as f32),
+            Self::Number(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    // the name of the literal's type, integers and floats are both numbers
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Int(_) | Self::Number(_) => "number",
+            Self::String(_) => "string",
+            Self::Char(_) => "char",
+            Self::Bool(_) => "bool",
+            Self::List(_) => "list",
+            Self::Block(_) => "block",
+        }
+    }
+
+    // orders two literals of the same kind, integers and floats
+    // are compared by their numeric value
+    pub fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a.partial_cmp(b),
+            (Self::String(a), Self::String(b)) => a.partial_cmp(b),
+            (Self::Char(a), Self::Char(b)) => a.partial_cmp(b),
+            (Self::Bool(a), Self::Bool(b)) => a.partial_cmp(b),
+            (a, b) => a.as_number()?.partial_cmp(&b.as_number()?),
+        }
+    }
+
+    // applies an arithmetic operation to two numeric literals, the result stays
+    // an integer when both sides are integers and is promoted to a float otherwise
+    fn arithmetic(
+        &self,
+        rhs: &Self,
+        int_op: fn(i64, i64) -> Option<i64>,
+        float_op: fn(f32, f32) -> f32,
+    ) -> Option<Result<Self, ConstantError>> {
+        if let (Self::Int(n), Self::Int(m)) = (self, rhs) {
+            return Some(
+                int_op(*n, *m)
+                    .map(Self::Int)
+                    .ok_or_else(|| ConstantError::InvalidOperation("Integer overflow".into())),
+            );
+        }
+
+        let n = self.as_number()?;
+        let m = rhs.as_number()?;
+        Some(Ok(Self::Number(float_op(n, m))))
+    }
+
+    // the error for an operation that can't be done between these two types
+    pub fn mismatch(&self, action: &str, rhs: &Self) -> ConstantError {
+        ConstantError::InvalidOperation(format!(
+            "Cannot {action} {} and {}",
+            self.type_name(),
+            rhs.type_name()
+        ))
+    }
+
+    // repeats a string n times, as long as the result stays a reasonable size
+    fn repeat(s: &str, n: usize) -> Result<Self, ConstantError> {
+        match s.len().checked_mul(n) {
+            Some(len) if len <= MAX_REPEAT_LEN => Ok(Self::String(s.repeat(n))),
+            _ => Err(ConstantError::InvalidOperation(
+                "Repeated string would be too long".into(),
+            )),
+        }
+    }
+
+    // moves a char's code point by n, for 'a' 1 + giving 'b'
+    fn shift_char(c: char, n: i64) -> Result<Self, ConstantError> {
+        (c as i64)
+            .checked_add(n)
+            .and_then(|code| u32::try_from(code).ok())
+            .and_then(char::from_u32)
+            .map(Self::Char)
+            .ok_or_else(|| ConstantError::InvalidOperation("Char code point out of range".into()))
+    }
 }
 
 impl Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Int(v) => f.write_fmt(format_args!("{v}")),
             Self::Number(v) => f.write_fmt(format_args!("{v}")),
             Self::String(v) => f.write_fmt(format_args!("{v}")),
+            Self::Char(v) => f.write_fmt(format_args!("{v}")),
             Self::Bool(v) => f.write_fmt(format_args!("{v}")),
+            Self::List(v) => {
+                let items = v.iter().map(|i| i.to_string()).collect::<Vec<String>>();
+                f.write_fmt(format_args!("[{}]", items.join(", ")))
+            }
+            Self::Block(_) => f.write_str("<block>"),
         }
     }
 }
@@ -93,28 +294,14 @@ impl Add<Literal> for Literal {
     type Output = Result<Self, ConstantError>;
 
     fn add(self, rhs: Self) -> Self::Output {
-        match self {
-            Self::Number(n) => {
-                if let Self::Number(m) = rhs {
-                    Ok(Self::Number(n + m))
-                } else {
-                    Err(ConstantError::InvalidOperation(
-                        "Can only add numbers to numbers".into(),
-                    ))
-                }
-            }
-            Self::Bool(_) => Err(ConstantError::InvalidOperation(
-                "Cannot add booleans".into(),
-            )),
-            Self::String(s) => {
-                if let Self::String(z) = rhs {
-                    Ok(Self::String(s + &z))
-                } else {
-                    Err(ConstantError::InvalidOperation(
-                        "Can only add strings to strings".into(),
-                    ))
-                }
-            }
+        if let Some(res) = self.arithmetic(&rhs, i64::checked_add, |n, m| n + m) {
+            return res;
+        }
+
+        match (self, rhs) {
+            (Self::String(s), Self::String(z)) => Ok(Self::String(s + &z)),
+            (Self::Char(c), Self::Int(n)) => Self::shift_char(c, n),
+            (a, b) => Err(a.mismatch("add", &b)),
         }
     }
 }
@@ -123,22 +310,15 @@ impl Sub<Literal> for Literal {
     type Output = Result<Self, ConstantError>;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        match self {
-            Self::Number(n) => {
-                if let Self::Number(m) = rhs {
-                    Ok(Self::Number(n - m))
-                } else {
-                    Err(ConstantError::InvalidOperation(
-                        "Can only subtract numbers from numbers".into(),
-                    ))
-                }
+        if let Some(res) = self.arithmetic(&rhs, i64::checked_sub, |n, m| n - m) {
+            return res;
+        }
+
+        match (self, rhs) {
+            (Self::Char(c), Self::Int(n)) => {
+                Self::shift_char(c, n.checked_neg().unwrap_or(i64::MAX))
             }
-            Self::Bool(_) => Err(ConstantError::InvalidOperation(
-                "Cannot subtract booleans".into(),
-            )),
-            Self::String(_) => Err(ConstantError::InvalidOperation(
-                "Cannot subtract strings".into(),
-            )),
+            (a, b) => Err(a.mismatch("subtract", &b)),
         }
     }
 }
@@ -147,28 +327,14 @@ impl Mul<Literal> for Literal {
     type Output = Result<Self, ConstantError>;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        match self {
-            Self::Number(n) => {
-                if let Self::Number(m) = rhs {
-                    Ok(Self::Number(n * m))
-                } else {
-                    Err(ConstantError::InvalidOperation(
-                        "Can only multiply numbers with numbers".into(),
-                    ))
-                }
-            }
-            Self::Bool(_) => Err(ConstantError::InvalidOperation(
-                "Cannot multiply booleans".into(),
-            )),
-            Self::String(s) => {
-                if let Self::Number(n) = rhs {
-                    Ok(Self::String(s.repeat(n as usize)))
-                } else {
-                    Err(ConstantError::InvalidOperation(
-                        "Can only multiply strings with numbers".into(),
-                    ))
-                }
-            }
+        if let Some(res) = self.arithmetic(&rhs, i64::checked_mul, |n, m| n * m) {
+            return res;
+        }
+
+        match (self, rhs) {
+            (Self::String(s), Self::Int(n)) => Self::repeat(&s, n.max(0) as usize),
+            (Self::String(s), Self::Number(n)) => Self::repeat(&s, n as usize),
+            (a, b) => Err(a.mismatch("multiply", &b)),
         }
     }
 }
@@ -176,24 +342,16 @@ impl Mul<Literal> for Literal {
 impl Div<Literal> for Literal {
     type Output = Result<Self, ConstantError>;
 
+    // division always produces a float, even between integers
     fn div(self, rhs: Self) -> Self::Output {
-        match self {
-            Self::Number(n) => {
-                if let Self::Number(m) = rhs {
-                    Ok(Self::Number(n / m))
-                } else {
-                    Err(ConstantError::InvalidOperation(
-                        "Can only divide numbers with number".into(),
-                    ))
-                }
+        if let (Some(n), Some(m)) = (self.as_number(), rhs.as_number()) {
+            if m == 0.0 {
+                return Err(ConstantError::InvalidOperation("Division by zero".into()));
             }
-            Self::Bool(_) => Err(ConstantError::InvalidOperation(
-                "Cannot divide with booleans".into(),
-            )),
-            Self::String(_) => Err(ConstantError::InvalidOperation(
-                "Cannot divide with strings".into(),
-            )),
+            return Ok(Self::Number(n / m));
         }
+
+        Err(self.mismatch("divide", &rhs))
     }
 }
 
@@ -201,22 +359,14 @@ impl Rem<Literal> for Literal {
     type Output = Result<Self, ConstantError>;
 
     fn rem(self, rhs: Self) -> Self::Output {
-        match self {
-            Self::Number(n) => {
-                if let Self::Number(m) = rhs {
-                    Ok(Self::Number(n % m))
-                } else {
-                    Err(ConstantError::InvalidOperation(
-                        "Can only mod numbers with numbers".into(),
-                    ))
-                }
-            }
-            Self::Bool(_) => Err(ConstantError::InvalidOperation(
-                "Cannot mod with booleans".into(),
-            )),
-            Self::String(_) => Err(ConstantError::InvalidOperation(
-                "Cannot mod with strings".into(),
-            )),
+        if self.as_number().is_some() && rhs.as_number() == Some(0.0) {
+            return Err(ConstantError::InvalidOperation("Modulo by zero".into()));
         }
+
+        if let Some(res) = self.arithmetic(&rhs, i64::checked_rem, |n, m| n % m) {
+            return res;
+        }
+
+        Err(self.mismatch("mod", &rhs))
     }
 }
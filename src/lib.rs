@@ -0,0 +1,58 @@
+pub use error::ConstantError;
+pub use interpreter::Interpreter;
+pub use lexer::{Lexer, Literal, Span, Token, TokenType};
+pub use parser::{
+    check_stack_effects, DoubleOpType, Parser, SingleOpType, StackOpType, Statement, TripleOpType,
+    Value,
+};
+
+pub mod error;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
+
+// lexes, parses, and interprets the given source in one go
+pub fn run(source: &str) -> Result<(), ConstantError> {
+    let tokens = Lexer::new(source).tokenize()?;
+    let ast = Parser::new(&tokens).parse()?;
+
+    Interpreter::new(ast).interpret()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_source() {
+        assert!(run("1 2 + drop").is_ok());
+        assert!(matches!(
+            run("1 +"),
+            Err(ConstantError::InvalidStackAmount(_, 2))
+        ));
+        assert!(matches!(
+            run("\"unterminated"),
+            Err(ConstantError::StringNotTerminated)
+        ));
+    }
+
+    #[test]
+    fn run_recursion() {
+        // runs on an ordinary thread, without the larger stack main.rs uses
+        assert!(matches!(
+            run("proc p do call p end call p"),
+            Err(ConstantError::RecursionLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn run_exit_codes() {
+        let code = |source| run(source).map_err(|e| e.exit_code()).err();
+
+        assert_eq!(code("1 2 + drop"), None);
+        assert_eq!(code("1 +"), Some(1));
+        assert_eq!(code("if true do 1"), Some(3));
+        assert_eq!(code("\"unterminated"), Some(3));
+        assert_eq!(code("7 exit"), Some(7));
+    }
+}
@@ -1,29 +1,127 @@
-use error::ConstantError;
-use interpreter::Interpreter;
-use lexer::Lexer;
-use parser::Parser;
+use std::time::Instant;
 
-mod error;
-mod interpreter;
-mod lexer;
-mod parser;
+use constant::{check_stack_effects, ConstantError, Interpreter, Lexer, Parser, Statement};
 
-fn main() -> Result<(), ConstantError> {
+// blocks are interpreted recursively, so the interpreter gets a larger native
+// stack than the main thread, which lets it raise the library's recursion limit
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+const RECURSION_LIMIT: usize = 10_000;
+
+fn main() {
+    let res = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .map_err(ConstantError::from)
+        .and_then(|handle| {
+            handle
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e))
+        });
+
+    if let Err(e) = res {
+        // 'exit' ends the program with its status code rather than as an error
+        if !matches!(e, ConstantError::Exit(_)) {
+            eprintln!("Error: {e}");
+        }
+        std::process::exit(e.exit_code());
+    }
+}
+
+// the REPL and programs both run on the larger stack, so both get the higher limit
+fn interpreter(program: Vec<Statement>) -> Interpreter {
+    let mut interpreter = Interpreter::new(program);
+    interpreter.set_recursion_limit(RECURSION_LIMIT);
+    interpreter
+}
+
+fn run() -> Result<(), ConstantError> {
     let args = std::env::args().collect::<Vec<String>>();
     if args.len() == 1 {
-        Interpreter::new(Vec::new()).repl();
-        Ok(())
-    } else if args.len() == 2 {
-        let file_contents = std::fs::read_to_string(&args[1])
-            .map_err(|_| ConstantError::SourceFileNotFound(args[1].clone()))?;
-
-        let tokens = Lexer::new(&file_contents).tokenize()?;
-        let ast = Parser::new(&tokens).parse()?;
-
-        Interpreter::new(ast).interpret()
-    } else if args.len() > 2 {
-        return Err(ConstantError::TooManyArgs);
-    } else {
+        interpreter(Vec::new()).repl();
+        return Ok(());
+    }
+
+    // flags come before the source
+    let mut args = &args[1..];
+    let mut print_ast = false;
+    let mut trace = false;
+    let mut strict = false;
+    let mut check = false;
+    let mut time = false;
+    while let Some(flag) = args.first() {
+        match flag.as_str() {
+            "--ast" => print_ast = true,
+            "--trace" => trace = true,
+            "--strict" => strict = true,
+            "--check" => check = true,
+            "--time" => time = true,
+            _ => break,
+        }
+        args = &args[1..];
+    }
+
+    let Some(first) = args.first() else {
         return Err(ConstantError::NoSourceFile);
+    };
+
+    // anything after the source is passed on to the program
+    let (source, program_args) = match first.as_str() {
+        "-e" | "--eval" => {
+            let source = args
+                .get(1)
+                .ok_or_else(|| ConstantError::NoEvalSource(first.clone()))?;
+            (source.clone(), &args[2..])
+        }
+        // a source file of '-' reads the program from stdin
+        "-" => (std::io::read_to_string(std::io::stdin())?, &args[1..]),
+        path => {
+            let file_contents = std::fs::read_to_string(path)
+                .map_err(|_| ConstantError::SourceFileNotFound(path.into()))?;
+            (file_contents, &args[1..])
+        }
+    };
+
+    // the times are reported even when a phase fails or the program isn't run
+    let mut times = Vec::new();
+    let res = (|| {
+        let start = Instant::now();
+        let tokens = Lexer::new(&source).tokenize();
+        times.push(("lexing", start.elapsed()));
+
+        let start = Instant::now();
+        let ast = Parser::new(&tokens?).parse();
+        times.push(("parsing", start.elapsed()));
+        let ast = ast?;
+
+        if check {
+            let start = Instant::now();
+            let res = check_stack_effects(&ast);
+            times.push(("checking", start.elapsed()));
+            res?;
+        }
+
+        if print_ast {
+            println!("{ast:#?}");
+            return Ok(());
+        }
+
+        let mut interpreter = interpreter(ast);
+        interpreter.set_args(program_args.to_vec());
+        interpreter.set_trace(trace);
+        interpreter.set_strict(strict);
+
+        let start = Instant::now();
+        let res = interpreter.interpret();
+        times.push(("interpreting", start.elapsed()));
+        res
+    })();
+
+    // the times go to stderr so they don't mix with the program's output
+    if time {
+        for (phase, elapsed) in times {
+            eprintln!("{phase}: {elapsed:?}");
+        }
     }
+
+    res
 }
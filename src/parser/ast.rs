@@ -5,16 +5,31 @@ pub enum Statement {
     Push(Value),
     DoubleOperation(DoubleOpType),
     SingleOperation(SingleOpType),
+    TripleOperation(TripleOpType),
+    StackOperation(StackOpType),
     Bind(String),
+    Const(String),
+    Unbind(String),
+    Defined(String),
     If(
         Vec<Statement>,
         Vec<Statement>,
         Vec<(Vec<Statement>, Vec<Statement>)>,
         Vec<Statement>,
     ),
+    // each case's conditions and body, and the else body
+    Cond(Vec<(Vec<Statement>, Vec<Statement>)>, Vec<Statement>),
     While(Vec<Statement>, Vec<Statement>),
-    Procedure(String, Vec<Statement>),
+    For(Vec<Statement>),
+    Repeat(Vec<Statement>, Vec<Statement>),
+    // run the block on top of the stack, once or a counted number of times
+    Apply,
+    Times,
+    // the name, the optional stack effect annotation, and the body
+    Procedure(String, Option<String>, Vec<Statement>),
     Call(String),
+    Break,
+    Continue,
     Empty,
 }
 
@@ -34,6 +49,25 @@ pub enum DoubleOpType {
     And,
     Or,
     Swap,
+    Over,
+    BAnd,
+    BOr,
+    BXor,
+    Shl,
+    Shr,
+    Min,
+    Max,
+    Pow,
+    CharAt,
+    ListPush,
+    TwoDup,
+    TwoDrop,
+    Nip,
+    Tuck,
+    IDiv,
+    Contains,
+    StartsWith,
+    EndsWith,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -41,10 +75,68 @@ pub enum SingleOpType {
     Print,
     Dup,
     Drop,
+    Not,
+    Arg,
+    ToNumber,
+    ToString,
+    Len,
+    Write,
+    Pick,
+    Sqrt,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Neg,
+    ToUpper,
+    ToLower,
+    ListLen,
+    Assert,
+    Exit,
+    Ord,
+    Chr,
+    Input,
+    Roll,
+    Trim,
+    TrimStart,
+    TrimEnd,
+    Reverse,
+    ToBool,
+    ToInt,
+    Seed,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TripleOpType {
+    Rot,
+    Select,
+    Substring,
+    ReverseRot,
+    Replace,
+}
+
+// operations that don't take a fixed number of values off the stack
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StackOpType {
+    Clear,
+    Depth,
+    Read,
+    Split,
+    ListNew,
+    Dump,
+    Format,
+    RollUp,
+    RollDown,
+    DupAll,
+    IsNumber,
+    IsString,
+    IsBool,
+    Rand,
 }
 
 #[derive(PartialEq, Clone, Debug)]
 pub enum Value {
     Literal(Literal),
     Ident(String),
+    Block(Vec<Statement>),
 }
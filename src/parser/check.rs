@@ -0,0 +1,300 @@
+use crate::error::ConstantError;
+
+use super::{DoubleOpType, SingleOpType, StackOpType, Statement, TripleOpType};
+
+// checks that no statement can take more values off the stack than the program has
+// put on it, the program is assumed to start with an empty stack. this is approximate,
+// once the depth can't be known (after a 'call', 'split', 'break', etc.) nothing is
+// checked until a 'clear' makes it known again
+pub fn check_stack_effects(program: &[Statement]) -> Result<(), ConstantError> {
+    check_block(program, Some(0)).map(|_| ())
+}
+
+// follows the stack depth through a block, giving the depth at the end of it
+fn check_block(
+    statements: &[Statement],
+    depth: Option<usize>,
+) -> Result<Option<usize>, ConstantError> {
+    statements
+        .iter()
+        .try_fold(depth, |depth, statement| check_statement(statement, depth))
+}
+
+fn check_statement(
+    statement: &Statement,
+    depth: Option<usize>,
+) -> Result<Option<usize>, ConstantError> {
+    let (name, needs, gives) = match statement {
+        Statement::Push(_) | Statement::Defined(_) => ("Push".into(), 0, 1),
+        Statement::Bind(_) | Statement::Const(_) => ("Bind".into(), 1, 0),
+        Statement::Unbind(_) | Statement::Procedure(..) | Statement::Empty => return Ok(depth),
+        // what a block does isn't known until it's applied
+        Statement::Apply => {
+            take(depth, "Apply", 1)?;
+            return Ok(None);
+        }
+        Statement::Times => {
+            take(depth, "Times", 2)?;
+            return Ok(None);
+        }
+        Statement::SingleOperation(o) => match single_effect(*o) {
+            Some(gives) => (format!("{o:?}"), 1, gives),
+            None => {
+                take(depth, &format!("{o:?}"), 1)?;
+                return Ok(None);
+            }
+        },
+        Statement::DoubleOperation(o) => (format!("{o:?}"), 2, double_effect(*o)),
+        Statement::TripleOperation(o) => (format!("{o:?}"), 3, triple_effect(*o)),
+        Statement::StackOperation(o) => match o {
+            StackOpType::Clear => return Ok(Some(0)),
+            StackOpType::Depth | StackOpType::Read | StackOpType::ListNew | StackOpType::Rand => {
+                ("Push".into(), 0, 1)
+            }
+            StackOpType::IsNumber | StackOpType::IsString | StackOpType::IsBool => {
+                (format!("{o:?}"), 1, 2)
+            }
+            StackOpType::DupAll => return Ok(depth.map(|d| d * 2)),
+            StackOpType::Dump | StackOpType::RollUp | StackOpType::RollDown => return Ok(depth),
+            // the number of values taken depends on the format string
+            StackOpType::Format => {
+                take(depth, "Format", 1)?;
+                return Ok(None);
+            }
+            // the number of pieces depends on the strings
+            StackOpType::Split => {
+                take(depth, "Split", 2)?;
+                return Ok(None);
+            }
+        },
+        Statement::If(conditions, statements, elifs, else_statements) => {
+            let clauses = std::iter::once(("If", &conditions[..], &statements[..]))
+                .chain(elifs.iter().map(|(c, s)| ("Elif", &c[..], &s[..])));
+            return check_branches("an if", clauses, else_statements, depth);
+        }
+        Statement::Cond(clauses, else_statements) => {
+            let clauses = clauses.iter().map(|(c, s)| ("Case", &c[..], &s[..]));
+            return check_branches("a cond", clauses, else_statements, depth);
+        }
+        Statement::While(conditions, statements) => {
+            let start = depth;
+            let depth = take(check_block(conditions, depth)?, "While", 1)?;
+
+            // the body has to leave the stack how the conditions found it
+            return match (start, check_block(statements, depth)?) {
+                (Some(start), Some(end)) if start != end => {
+                    Err(ConstantError::StackEffectMismatch(
+                        "the body of a while loop changes the depth of the stack".into(),
+                    ))
+                }
+                (_, None) => Ok(None),
+                _ => Ok(depth),
+            };
+        }
+        Statement::For(statements) => {
+            let depth = take(depth, "For", 2)?;
+
+            // each number pushed for the body has to be used up by it
+            return match (depth, check_block(statements, depth.map(|d| d + 1))?) {
+                (Some(start), Some(end)) if start != end => {
+                    Err(ConstantError::StackEffectMismatch(
+                        "the body of a for loop changes the depth of the stack".into(),
+                    ))
+                }
+                (_, None) => Ok(None),
+                _ => Ok(depth),
+            };
+        }
+        Statement::Repeat(count, statements) => {
+            let depth = take(check_block(count, depth)?, "Repeat", 1)?;
+
+            return match (depth, check_block(statements, depth)?) {
+                (Some(start), Some(end)) if start != end => {
+                    Err(ConstantError::StackEffectMismatch(
+                        "the body of a repeat loop changes the depth of the stack".into(),
+                    ))
+                }
+                (_, None) => Ok(None),
+                _ => Ok(depth),
+            };
+        }
+        Statement::Call(_) | Statement::Break | Statement::Continue => return Ok(None),
+    };
+
+    Ok(take(depth, &name, needs)?.map(|d| d + gives))
+}
+
+// takes values off the stack, erroring if there can't be enough of them
+fn take(depth: Option<usize>, name: &str, needs: usize) -> Result<Option<usize>, ConstantError> {
+    match depth {
+        Some(depth) if depth < needs => Err(ConstantError::StackEffectMismatch(format!(
+            "{name} needs {needs} items on the stack but there would only be {depth}"
+        ))),
+        Some(depth) => Ok(Some(depth - needs)),
+        None => Ok(None),
+    }
+}
+
+// each clause's conditions leave a boolean to be taken, and every branch,
+// including the else, has to leave the stack at the same depth
+fn check_branches<'a>(
+    statement: &str,
+    clauses: impl Iterator<Item = (&'a str, &'a [Statement], &'a [Statement])>,
+    else_statements: &[Statement],
+    mut depth: Option<usize>,
+) -> Result<Option<usize>, ConstantError> {
+    let mut branches = Vec::new();
+    for (name, conditions, statements) in clauses {
+        depth = take(check_block(conditions, depth)?, name, 1)?;
+        branches.push(check_block(statements, depth)?);
+    }
+    branches.push(check_block(else_statements, depth)?);
+
+    match branches.into_iter().collect::<Option<Vec<usize>>>() {
+        Some(depths) if depths.windows(2).any(|w| w[0] != w[1]) => {
+            Err(ConstantError::StackEffectMismatch(format!(
+                "the branches of {statement} statement leave the stack at different depths"
+            )))
+        }
+        Some(depths) => Ok(depths.first().copied()),
+        None => Ok(None),
+    }
+}
+
+// how many values each operation puts back after taking its operands, which
+// is unknown for 'roll' since how deep it reaches depends on the count
+fn single_effect(o: SingleOpType) -> Option<usize> {
+    Some(match o {
+        SingleOpType::Print
+        | SingleOpType::Write
+        | SingleOpType::Drop
+        | SingleOpType::Assert
+        | SingleOpType::Exit
+        | SingleOpType::Seed => 0,
+        SingleOpType::Dup => 2,
+        SingleOpType::Roll => return None,
+        SingleOpType::Not
+        | SingleOpType::Arg
+        | SingleOpType::ToNumber
+        | SingleOpType::ToString
+        | SingleOpType::Len
+        | SingleOpType::Pick
+        | SingleOpType::Sqrt
+        | SingleOpType::Abs
+        | SingleOpType::Floor
+        | SingleOpType::Ceil
+        | SingleOpType::Round
+        | SingleOpType::Neg
+        | SingleOpType::ToUpper
+        | SingleOpType::ToLower
+        | SingleOpType::ListLen
+        | SingleOpType::Ord
+        | SingleOpType::Chr
+        | SingleOpType::Input
+        | SingleOpType::Trim
+        | SingleOpType::TrimStart
+        | SingleOpType::TrimEnd
+        | SingleOpType::Reverse
+        | SingleOpType::ToBool
+        | SingleOpType::ToInt => 1,
+    })
+}
+
+fn double_effect(o: DoubleOpType) -> usize {
+    match o {
+        DoubleOpType::TwoDrop => 0,
+        DoubleOpType::Add
+        | DoubleOpType::Sub
+        | DoubleOpType::Mul
+        | DoubleOpType::Div
+        | DoubleOpType::IDiv
+        | DoubleOpType::Mod
+        | DoubleOpType::GT
+        | DoubleOpType::GTEq
+        | DoubleOpType::LT
+        | DoubleOpType::LTEq
+        | DoubleOpType::Eq
+        | DoubleOpType::NotEq
+        | DoubleOpType::And
+        | DoubleOpType::Or
+        | DoubleOpType::BAnd
+        | DoubleOpType::BOr
+        | DoubleOpType::BXor
+        | DoubleOpType::Shl
+        | DoubleOpType::Shr
+        | DoubleOpType::Min
+        | DoubleOpType::Max
+        | DoubleOpType::Pow
+        | DoubleOpType::CharAt
+        | DoubleOpType::Contains
+        | DoubleOpType::StartsWith
+        | DoubleOpType::EndsWith
+        | DoubleOpType::ListPush
+        | DoubleOpType::Nip => 1,
+        DoubleOpType::Swap => 2,
+        DoubleOpType::Over | DoubleOpType::Tuck => 3,
+        DoubleOpType::TwoDup => 4,
+    }
+}
+
+fn triple_effect(o: TripleOpType) -> usize {
+    match o {
+        TripleOpType::Select | TripleOpType::Substring | TripleOpType::Replace => 1,
+        TripleOpType::Rot | TripleOpType::ReverseRot => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    use super::*;
+
+    fn check(source: &str) -> Result<(), ConstantError> {
+        let tokens = Lexer::new(source).tokenize()?;
+        check_stack_effects(&Parser::new(&tokens).parse()?)
+    }
+
+    #[test]
+    fn check_underflow() -> Result<(), ConstantError> {
+        check("1 2 + print")?;
+        check("1 dup * 2 swap - drop")?;
+        assert!(matches!(
+            check("1 +"),
+            Err(ConstantError::StackEffectMismatch(_))
+        ));
+        assert!(matches!(
+            check("1 2 + drop drop"),
+            Err(ConstantError::StackEffectMismatch(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_unknown_depth() -> Result<(), ConstantError> {
+        // the procedure could push anything so nothing after it is checked
+        check("proc p do 1 end call p +")?;
+        assert!(check("proc p do 1 end call p clear +").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_blocks() -> Result<(), ConstantError> {
+        check("1 if true do 2 + else do 3 * end print")?;
+        check("0 while dup 10 < do 1 + end drop")?;
+        check("0 0 10 for do + end print")?;
+        check("0 3 repeat do 1 + end print")?;
+        assert!(check("3 repeat do 1 end").is_err());
+        assert!(check("if true do 1 end").is_err());
+        assert!(check("if true do 1 elif false do 2 else do end").is_err());
+        check("cond case true do 1 case false do 2 else do 3 end print")?;
+        assert!(check("cond case true do 1 else do end").is_err());
+        assert!(check("cond case drop do end").is_err());
+        assert!(check("while true do 1 end").is_err());
+        assert!(check("0 10 for do end").is_err());
+
+        Ok(())
+    }
+}
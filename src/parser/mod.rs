@@ -4,10 +4,12 @@ use crate::{
     error::ConstantError,
     lexer::{Token, TokenType},
 };
-pub use ast::{DoubleOpType, SingleOpType, Statement, Value};
+pub use ast::{DoubleOpType, SingleOpType, StackOpType, Statement, TripleOpType, Value};
+pub use check::check_stack_effects;
 use lazy_static::lazy_static;
 
 mod ast;
+mod check;
 
 lazy_static! {
     static ref DOUBLE_OPERATIONS: HashMap<TokenType, DoubleOpType> = {
@@ -25,7 +27,26 @@ lazy_static! {
         h.insert(TokenType::And, DoubleOpType::And);
         h.insert(TokenType::Or, DoubleOpType::Or);
         h.insert(TokenType::Swap, DoubleOpType::Swap);
+        h.insert(TokenType::Over, DoubleOpType::Over);
         h.insert(TokenType::Percent, DoubleOpType::Mod);
+        h.insert(TokenType::BAnd, DoubleOpType::BAnd);
+        h.insert(TokenType::BOr, DoubleOpType::BOr);
+        h.insert(TokenType::BXor, DoubleOpType::BXor);
+        h.insert(TokenType::Shl, DoubleOpType::Shl);
+        h.insert(TokenType::Shr, DoubleOpType::Shr);
+        h.insert(TokenType::Min, DoubleOpType::Min);
+        h.insert(TokenType::Max, DoubleOpType::Max);
+        h.insert(TokenType::Pow, DoubleOpType::Pow);
+        h.insert(TokenType::CharAt, DoubleOpType::CharAt);
+        h.insert(TokenType::ListPush, DoubleOpType::ListPush);
+        h.insert(TokenType::TwoDup, DoubleOpType::TwoDup);
+        h.insert(TokenType::TwoDrop, DoubleOpType::TwoDrop);
+        h.insert(TokenType::Nip, DoubleOpType::Nip);
+        h.insert(TokenType::Tuck, DoubleOpType::Tuck);
+        h.insert(TokenType::IDiv, DoubleOpType::IDiv);
+        h.insert(TokenType::Contains, DoubleOpType::Contains);
+        h.insert(TokenType::StartsWith, DoubleOpType::StartsWith);
+        h.insert(TokenType::EndsWith, DoubleOpType::EndsWith);
         h
     };
     static ref SINGLE_OPERATIONS: HashMap<TokenType, SingleOpType> = {
@@ -33,6 +54,62 @@ lazy_static! {
         h.insert(TokenType::Print, SingleOpType::Print);
         h.insert(TokenType::Dup, SingleOpType::Dup);
         h.insert(TokenType::Drop, SingleOpType::Drop);
+        h.insert(TokenType::Not, SingleOpType::Not);
+        h.insert(TokenType::Arg, SingleOpType::Arg);
+        h.insert(TokenType::ToNumber, SingleOpType::ToNumber);
+        h.insert(TokenType::ToString, SingleOpType::ToString);
+        h.insert(TokenType::Len, SingleOpType::Len);
+        h.insert(TokenType::Write, SingleOpType::Write);
+        h.insert(TokenType::Pick, SingleOpType::Pick);
+        h.insert(TokenType::Sqrt, SingleOpType::Sqrt);
+        h.insert(TokenType::Abs, SingleOpType::Abs);
+        h.insert(TokenType::Floor, SingleOpType::Floor);
+        h.insert(TokenType::Ceil, SingleOpType::Ceil);
+        h.insert(TokenType::Round, SingleOpType::Round);
+        h.insert(TokenType::Neg, SingleOpType::Neg);
+        h.insert(TokenType::ToUpper, SingleOpType::ToUpper);
+        h.insert(TokenType::ToLower, SingleOpType::ToLower);
+        h.insert(TokenType::ListLen, SingleOpType::ListLen);
+        h.insert(TokenType::Assert, SingleOpType::Assert);
+        h.insert(TokenType::Exit, SingleOpType::Exit);
+        h.insert(TokenType::Ord, SingleOpType::Ord);
+        h.insert(TokenType::Chr, SingleOpType::Chr);
+        h.insert(TokenType::Input, SingleOpType::Input);
+        h.insert(TokenType::Roll, SingleOpType::Roll);
+        h.insert(TokenType::Trim, SingleOpType::Trim);
+        h.insert(TokenType::TrimStart, SingleOpType::TrimStart);
+        h.insert(TokenType::TrimEnd, SingleOpType::TrimEnd);
+        h.insert(TokenType::Reverse, SingleOpType::Reverse);
+        h.insert(TokenType::ToBool, SingleOpType::ToBool);
+        h.insert(TokenType::ToInt, SingleOpType::ToInt);
+        h.insert(TokenType::Seed, SingleOpType::Seed);
+        h
+    };
+    static ref TRIPLE_OPERATIONS: HashMap<TokenType, TripleOpType> = {
+        let mut h = HashMap::new();
+        h.insert(TokenType::Rot, TripleOpType::Rot);
+        h.insert(TokenType::Select, TripleOpType::Select);
+        h.insert(TokenType::Substring, TripleOpType::Substring);
+        h.insert(TokenType::ReverseRot, TripleOpType::ReverseRot);
+        h.insert(TokenType::Replace, TripleOpType::Replace);
+        h
+    };
+    static ref STACK_OPERATIONS: HashMap<TokenType, StackOpType> = {
+        let mut h = HashMap::new();
+        h.insert(TokenType::Clear, StackOpType::Clear);
+        h.insert(TokenType::Depth, StackOpType::Depth);
+        h.insert(TokenType::Read, StackOpType::Read);
+        h.insert(TokenType::Split, StackOpType::Split);
+        h.insert(TokenType::ListNew, StackOpType::ListNew);
+        h.insert(TokenType::Dump, StackOpType::Dump);
+        h.insert(TokenType::Format, StackOpType::Format);
+        h.insert(TokenType::RollUp, StackOpType::RollUp);
+        h.insert(TokenType::RollDown, StackOpType::RollDown);
+        h.insert(TokenType::DupAll, StackOpType::DupAll);
+        h.insert(TokenType::IsNumber, StackOpType::IsNumber);
+        h.insert(TokenType::IsString, StackOpType::IsString);
+        h.insert(TokenType::IsBool, StackOpType::IsBool);
+        h.insert(TokenType::Rand, StackOpType::Rand);
         h
     };
 }
@@ -41,6 +118,8 @@ pub struct Parser<'a> {
     tokens: &'a Vec<Token>,
     current_token: Token,
     current_pos: usize,
+    // the opening tokens of the blocks currently being parsed
+    blocks: Vec<Token>,
 }
 
 impl<'a> Parser<'a> {
@@ -49,6 +128,7 @@ impl<'a> Parser<'a> {
             tokens,
             current_token: tokens[0].clone(),
             current_pos: 0,
+            blocks: Vec::new(),
         }
     }
 
@@ -59,6 +139,16 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn unexpected_token(&self) -> ConstantError {
+        // running out of tokens inside a block means its end is missing
+        if let (TokenType::EOF, Some(opener)) = (self.current_token.token_type, self.blocks.last())
+        {
+            return ConstantError::UnterminatedBlock(opener.lexeme.clone(), opener.span);
+        }
+
+        ConstantError::UnexpectedToken(self.current_token.token_type, self.current_token.span)
+    }
+
     fn check_token(&self, token: TokenType) -> bool {
         self.current_token.token_type == token
     }
@@ -69,12 +159,22 @@ impl<'a> Parser<'a> {
             self.next();
             Ok(tok)
         } else {
-            Err(ConstantError::UnexpectedToken(
-                self.current_token.token_type,
-            ))
+            Err(self.unexpected_token())
         }
     }
 
+    fn open_block(&mut self, token: TokenType) -> Result<(), ConstantError> {
+        let opener = self.match_token(token)?;
+        self.blocks.push(opener);
+        Ok(())
+    }
+
+    fn close_block(&mut self, token: TokenType) -> Result<(), ConstantError> {
+        self.match_token(token)?;
+        self.blocks.pop();
+        Ok(())
+    }
+
     pub fn parse(&mut self) -> Result<Vec<Statement>, ConstantError> {
         let mut ast = Vec::new();
 
@@ -93,22 +193,46 @@ impl<'a> Parser<'a> {
         } else if let Some(o) = DOUBLE_OPERATIONS.get(&self.current_token.token_type) {
             self.next();
             Ok(Statement::DoubleOperation(*o))
+        } else if let Some(o) = TRIPLE_OPERATIONS.get(&self.current_token.token_type) {
+            self.next();
+            Ok(Statement::TripleOperation(*o))
+        } else if let Some(o) = STACK_OPERATIONS.get(&self.current_token.token_type) {
+            self.next();
+            Ok(Statement::StackOperation(*o))
         } else if matches!(
             self.current_token.token_type,
-            TokenType::Number | TokenType::Bool | TokenType::String
+            TokenType::Number | TokenType::Bool | TokenType::String | TokenType::Char
         ) {
             let val = self.current_token.literal.clone().unwrap();
             self.next();
             Ok(Statement::Push(Value::Literal(val)))
+        } else if self.check_token(TokenType::LBracket) {
+            // blocks are pushed as values instead of being run
+            self.open_block(TokenType::LBracket)?;
+            let statements = self.get_statements_till(vec![TokenType::RBracket])?;
+            self.close_block(TokenType::RBracket)?;
+            Ok(Statement::Push(Value::Block(statements)))
         } else if self.check_token(TokenType::Bind) {
             self.match_token(TokenType::Bind)?;
             let ident = self.match_token(TokenType::Ident)?;
             Ok(Statement::Bind(ident.lexeme))
+        } else if self.check_token(TokenType::Const) {
+            self.match_token(TokenType::Const)?;
+            let ident = self.match_token(TokenType::Ident)?;
+            Ok(Statement::Const(ident.lexeme))
+        } else if self.check_token(TokenType::Unbind) {
+            self.match_token(TokenType::Unbind)?;
+            let ident = self.match_token(TokenType::Ident)?;
+            Ok(Statement::Unbind(ident.lexeme))
+        } else if self.check_token(TokenType::Defined) {
+            self.match_token(TokenType::Defined)?;
+            let ident = self.match_token(TokenType::Ident)?;
+            Ok(Statement::Defined(ident.lexeme))
         } else if self.check_token(TokenType::Ident) {
             let tok = self.match_token(TokenType::Ident)?;
             Ok(Statement::Push(Value::Ident(tok.lexeme)))
         } else if self.check_token(TokenType::If) {
-            self.match_token(TokenType::If)?;
+            self.open_block(TokenType::If)?;
             let (conditions, statements) = self.if_block()?;
 
             let mut elifs = Vec::new();
@@ -124,7 +248,7 @@ impl<'a> Parser<'a> {
 
                 else_statements = self.get_statements_till(vec![TokenType::End])?;
             }
-            self.match_token(TokenType::End)?;
+            self.close_block(TokenType::End)?;
 
             Ok(Statement::If(
                 conditions,
@@ -132,31 +256,92 @@ impl<'a> Parser<'a> {
                 elifs,
                 else_statements,
             ))
+        } else if self.check_token(TokenType::Cond) {
+            // a flatter if where every clause starts with 'case'
+            self.open_block(TokenType::Cond)?;
+
+            let mut clauses = Vec::new();
+            loop {
+                self.match_token(TokenType::Case)?;
+                clauses.push(self.if_block()?);
+                if !self.check_token(TokenType::Case) {
+                    break;
+                }
+            }
+
+            let mut else_statements = Vec::new();
+            if self.check_token(TokenType::Else) {
+                self.match_token(TokenType::Else)?;
+                self.match_token(TokenType::Do)?;
+
+                else_statements = self.get_statements_till(vec![TokenType::End])?;
+            }
+            self.close_block(TokenType::End)?;
+
+            Ok(Statement::Cond(clauses, else_statements))
         } else if self.check_token(TokenType::While) {
-            self.match_token(TokenType::While)?;
+            self.open_block(TokenType::While)?;
             let conditions = self.get_statements_till(vec![TokenType::Do])?;
 
             self.match_token(TokenType::Do)?;
 
             let statements = self.get_statements_till(vec![TokenType::End])?;
-            self.match_token(TokenType::End)?;
+            self.close_block(TokenType::End)?;
 
             Ok(Statement::While(conditions, statements))
+        } else if self.check_token(TokenType::For) {
+            self.open_block(TokenType::For)?;
+            self.match_token(TokenType::Do)?;
+
+            let statements = self.get_statements_till(vec![TokenType::End])?;
+            self.close_block(TokenType::End)?;
+
+            Ok(Statement::For(statements))
+        } else if self.check_token(TokenType::Repeat) {
+            // the count can come from the stack or from statements before the 'do'
+            self.open_block(TokenType::Repeat)?;
+            let count = self.get_statements_till(vec![TokenType::Do])?;
+
+            self.match_token(TokenType::Do)?;
+
+            let statements = self.get_statements_till(vec![TokenType::End])?;
+            self.close_block(TokenType::End)?;
+
+            Ok(Statement::Repeat(count, statements))
         } else if self.check_token(TokenType::Proc) {
-            self.match_token(TokenType::Proc)?;
+            self.open_block(TokenType::Proc)?;
             let ident = self.match_token(TokenType::Ident)?;
+
+            // only the text between the parentheses is kept, it isn't checked yet
+            let mut effect = None;
+            if self.check_token(TokenType::StackEffect) {
+                let tok = self.match_token(TokenType::StackEffect)?;
+                let text = &tok.lexeme[1..tok.lexeme.len() - 1];
+                effect = Some(text.trim().to_string());
+            }
+
             self.match_token(TokenType::Do)?;
             let statements = self.get_statements_till(vec![TokenType::End])?;
-            self.match_token(TokenType::End)?;
-            Ok(Statement::Procedure(ident.lexeme, statements))
+            self.close_block(TokenType::End)?;
+            Ok(Statement::Procedure(ident.lexeme, effect, statements))
         } else if self.check_token(TokenType::Call) {
             self.match_token(TokenType::Call)?;
             let ident = self.match_token(TokenType::Ident)?;
             Ok(Statement::Call(ident.lexeme))
+        } else if self.check_token(TokenType::Apply) {
+            self.match_token(TokenType::Apply)?;
+            Ok(Statement::Apply)
+        } else if self.check_token(TokenType::Times) {
+            self.match_token(TokenType::Times)?;
+            Ok(Statement::Times)
+        } else if self.check_token(TokenType::Break) {
+            self.match_token(TokenType::Break)?;
+            Ok(Statement::Break)
+        } else if self.check_token(TokenType::Continue) {
+            self.match_token(TokenType::Continue)?;
+            Ok(Statement::Continue)
         } else {
-            Err(ConstantError::UnexpectedToken(
-                self.current_token.token_type,
-            ))
+            Err(self.unexpected_token())
         }
     }
 
@@ -167,8 +352,12 @@ impl<'a> Parser<'a> {
 
         self.match_token(TokenType::Do)?;
 
-        let statements =
-            self.get_statements_till(vec![TokenType::Elif, TokenType::Else, TokenType::End])?;
+        let statements = self.get_statements_till(vec![
+            TokenType::Elif,
+            TokenType::Case,
+            TokenType::Else,
+            TokenType::End,
+        ])?;
 
         Ok((conditions, statements))
     }
@@ -179,14 +368,7 @@ impl<'a> Parser<'a> {
     ) -> Result<Vec<Statement>, ConstantError> {
         let mut statements = Vec::new();
         while !tokens.contains(&self.current_token.token_type) {
-            match self.statement() {
-                Ok(statement) => statements.push(statement),
-                Err(_) => {
-                    return Err(ConstantError::UnexpectedToken(
-                        self.current_token.token_type,
-                    ))
-                }
-            }
+            statements.push(self.statement()?);
         }
 
         Ok(statements)
@@ -195,7 +377,7 @@ impl<'a> Parser<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::{Lexer, Literal};
+    use crate::lexer::{Lexer, Literal, Span};
 
     use super::*;
 
@@ -206,10 +388,7 @@ mod tests {
         let ast = Parser::new(&tok).parse()?;
 
         assert_eq!(ast.len(), 4);
-        assert_eq!(
-            ast[0],
-            Statement::Push(Value::Literal(Literal::Number(1.0)))
-        );
+        assert_eq!(ast[0], Statement::Push(Value::Literal(Literal::Int(1))));
         assert_eq!(
             ast[1],
             Statement::Push(Value::Literal(Literal::String("test".into())))
@@ -234,6 +413,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parse_defined() -> Result<(), ConstantError> {
+        let tok = Lexer::new("defined x").tokenize()?;
+        let ast = Parser::new(&tok).parse()?;
+
+        assert_eq!(ast[0], Statement::Defined("x".into()));
+        assert!(Parser::new(&Lexer::new("defined 1").tokenize()?)
+            .parse()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_const() -> Result<(), ConstantError> {
+        let tok = Lexer::new("1 const x").tokenize()?;
+        let ast = Parser::new(&tok).parse()?;
+
+        assert_eq!(ast[1], Statement::Const("x".into()));
+        assert!(Parser::new(&Lexer::new("const 1").tokenize()?)
+            .parse()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_block() -> Result<(), ConstantError> {
+        let tok = Lexer::new("[ 1 [ ] ]").tokenize()?;
+        let ast = Parser::new(&tok).parse()?;
+
+        assert_eq!(
+            ast[0],
+            Statement::Push(Value::Block(vec![
+                Statement::Push(Value::Literal(Literal::Int(1))),
+                Statement::Push(Value::Block(vec![])),
+            ]))
+        );
+
+        let tok = Lexer::new("[ ] apply 2 [ ] times").tokenize()?;
+        let ast = Parser::new(&tok).parse()?;
+        assert_eq!(ast[1], Statement::Apply);
+        assert_eq!(ast[4], Statement::Times);
+
+        let tok = Lexer::new("[ 1").tokenize()?;
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnterminatedBlock(b, Span { line: 1, column: 1, len: 1 })) if b == "["
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn parse_if() -> Result<(), ConstantError> {
         let source = "if true do \"hello\" print end";
@@ -256,4 +488,203 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn parse_elif() -> Result<(), ConstantError> {
+        let source = "if false do 1 elif true do 2 elif false do 3 end";
+        let tok = Lexer::new(source).tokenize()?;
+        let ast = Parser::new(&tok).parse()?;
+
+        let push = |v| vec![Statement::Push(Value::Literal(v))];
+        assert_eq!(
+            ast[0],
+            Statement::If(
+                push(Literal::Bool(false)),
+                push(Literal::Int(1)),
+                vec![
+                    (push(Literal::Bool(true)), push(Literal::Int(2))),
+                    (push(Literal::Bool(false)), push(Literal::Int(3))),
+                ],
+                vec![],
+            )
+        );
+
+        // an elif can't come after the else
+        let tok = Lexer::new("if true do 1 else do 2 elif true do 3 end").tokenize()?;
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnexpectedToken(
+                TokenType::Elif,
+                Span {
+                    line: 1,
+                    column: 24,
+                    len: 4
+                }
+            ))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_cond() -> Result<(), ConstantError> {
+        let source = "cond case false do 1 case true do 2 else do 3 end";
+        let tok = Lexer::new(source).tokenize()?;
+        let ast = Parser::new(&tok).parse()?;
+
+        let push = |v| vec![Statement::Push(Value::Literal(v))];
+        assert_eq!(
+            ast[0],
+            Statement::Cond(
+                vec![
+                    (push(Literal::Bool(false)), push(Literal::Int(1))),
+                    (push(Literal::Bool(true)), push(Literal::Int(2))),
+                ],
+                push(Literal::Int(3)),
+            )
+        );
+
+        // there has to be at least one case, and cases can't be mixed into an if
+        let tok = Lexer::new("cond else do 1 end").tokenize()?;
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnexpectedToken(TokenType::Else, _))
+        ));
+        let tok = Lexer::new("if true do 1 case true do 2 end").tokenize()?;
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnexpectedToken(TokenType::Case, _))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_proc_stack_effect() -> Result<(), ConstantError> {
+        let tok = Lexer::new("proc add3 ( a b c -- sum ) do + + end").tokenize()?;
+        let ast = Parser::new(&tok).parse()?;
+
+        assert_eq!(
+            ast[0],
+            Statement::Procedure(
+                "add3".into(),
+                Some("a b c -- sum".into()),
+                vec![
+                    Statement::DoubleOperation(DoubleOpType::Add),
+                    Statement::DoubleOperation(DoubleOpType::Add)
+                ]
+            )
+        );
+
+        let tok = Lexer::new("proc p do end").tokenize()?;
+        let ast = Parser::new(&tok).parse()?;
+        assert_eq!(ast[0], Statement::Procedure("p".into(), None, vec![]));
+
+        // annotations only go between a procedure's name and its body
+        let tok = vec![
+            Token::new(TokenType::StackEffect, "( a -- a )".into(), None),
+            Token::eof(),
+        ];
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnexpectedToken(TokenType::StackEffect, _))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_nested_error() -> Result<(), ConstantError> {
+        let source = "proc p do\n    1 bind 2\nend";
+        let tok = Lexer::new(source).tokenize()?;
+
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnexpectedToken(
+                TokenType::Number,
+                Span {
+                    line: 2,
+                    column: 12,
+                    len: 1
+                }
+            ))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_unterminated_block() -> Result<(), ConstantError> {
+        let source = "1 bind x\nif x 1 == do\n    \"one\" print\n";
+        let tok = Lexer::new(source).tokenize()?;
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnterminatedBlock(b, Span { line: 2, column: 1, len: 2 })) if b == "if"
+        ));
+
+        let source = "proc p do\n    while true do end\n";
+        let tok = Lexer::new(source).tokenize()?;
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnterminatedBlock(b, Span { line: 1, column: 1, len: 4 })) if b == "proc"
+        ));
+
+        let source = "while true do if true do end";
+        let tok = Lexer::new(source).tokenize()?;
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnterminatedBlock(b, Span { line: 1, column: 1, len: 5 })) if b == "while"
+        ));
+
+        let source = "1 end";
+        let tok = Lexer::new(source).tokenize()?;
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnexpectedToken(
+                TokenType::End,
+                Span {
+                    line: 1,
+                    column: 3,
+                    len: 3
+                }
+            ))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_unexpected_token_position() -> Result<(), ConstantError> {
+        let source = "1 2 +\nbind do";
+        let tok = Lexer::new(source).tokenize()?;
+
+        assert!(matches!(
+            Parser::new(&tok).parse(),
+            Err(ConstantError::UnexpectedToken(
+                TokenType::Do,
+                Span {
+                    line: 2,
+                    column: 6,
+                    len: 2
+                }
+            ))
+        ));
+
+        // the span covers the whole token as written, quotes and escapes included
+        let source = "proc p do\n  \"a\\tb\" bind \"x\"\nend";
+        let tok = Lexer::new(source).tokenize()?;
+        match Parser::new(&tok).parse() {
+            Err(ConstantError::UnexpectedToken(TokenType::String, span)) => assert_eq!(
+                span,
+                Span {
+                    line: 2,
+                    column: 15,
+                    len: 3
+                }
+            ),
+            res => panic!("expected an unexpected string, got {res:?}"),
+        }
+
+        Ok(())
+    }
 }